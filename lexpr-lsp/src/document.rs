@@ -0,0 +1,41 @@
+use lexpr::{LineColumn, LineIndex, Span};
+use lsp_types::{Position, Range};
+
+/// The text of an open document, with the index to convert between the
+/// byte offsets of Lexpr spans and the UTF-16 positions of the protocol.
+pub(crate) struct Document {
+    pub(crate) text: String,
+    line_index: LineIndex,
+}
+
+impl Document {
+    pub(crate) fn new(text: String) -> Self {
+        let line_index = LineIndex::new(&text);
+        Self { text, line_index }
+    }
+
+    pub(crate) fn position(&self, byte_offset: usize) -> Position {
+        let LineColumn { line, column } = self.line_index.line_column_utf16(byte_offset);
+        Position::new(line as u32, column as u32)
+    }
+
+    pub(crate) fn range(&self, span: Span) -> Range {
+        Range::new(
+            self.position(span.start.byte_index),
+            self.position(span.end.byte_index),
+        )
+    }
+
+    /// The byte offset of `position`, `None` when it is outside of the text.
+    pub(crate) fn offset(&self, position: Position) -> Option<usize> {
+        self.line_index.offset_utf16(LineColumn {
+            line: position.line as usize,
+            column: position.character as usize,
+        })
+    }
+
+    /// The range from the start to the end of the text.
+    pub(crate) fn full_range(&self) -> Range {
+        Range::new(Position::new(0, 0), self.position(self.text.len()))
+    }
+}
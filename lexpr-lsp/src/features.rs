@@ -0,0 +1,277 @@
+use lexpr::{
+    cst::{parse_cst, SyntaxKind},
+    tokenizer::{TokenKind, Tokenizer},
+    FormatError, FormatOptions, ParseOptions, PrettyOptions, Scheme, Sexp, Span,
+};
+use lsp_types::{
+    DiagnosticSeverity, FoldingRange, Hover, HoverContents, MarkupContent, MarkupKind,
+    SemanticToken, SemanticTokenType, TextEdit,
+};
+
+use crate::document::Document;
+
+/// The semantic token types, in the order of their index in the legend.
+pub(crate) const TOKEN_TYPES: [SemanticTokenType; 6] = [
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+];
+const FUNCTION: u32 = 0;
+const OPERATOR: u32 = 1;
+const VARIABLE: u32 = 2;
+const NUMBER: u32 = 3;
+const STRING: u32 = 4;
+const COMMENT: u32 = 5;
+
+/// Every parse error of the document, with the hint naming the rule involved.
+pub(crate) fn diagnostics(
+    document: &Document,
+    options: &ParseOptions,
+) -> Vec<lsp_types::Diagnostic> {
+    let (_, errors) = lexpr::parse_str_recovering(&document.text, options.clone());
+    errors
+        .iter()
+        .map(|error| {
+            let diagnostic = lexpr::Diagnostic::from(error);
+            let message = match diagnostic.hint {
+                Some(hint) => format!("{}\nhint: {}", diagnostic.message, hint),
+                None => diagnostic.message,
+            };
+            lsp_types::Diagnostic {
+                range: document.range(diagnostic.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("lexpr".to_string()),
+                message,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The S-expression of the form at `offset`, pretty-printed for Scheme, and
+/// the rule that made the innermost expression there.
+pub(crate) fn hover(document: &Document, options: &ParseOptions, offset: usize) -> Option<Hover> {
+    let (sexps, _) = lexpr::parse_str_recovering(&document.text, options.clone());
+    let sexp = sexps.iter().find(|sexp| {
+        let span = sexp.span();
+        span.start.byte_index <= offset && offset <= span.end.byte_index
+    })?;
+    let mut value = format!(
+        "```scheme\n{}\n```",
+        lexpr::pretty_print(sexp, &Scheme, &PrettyOptions::default()).ok()?
+    );
+    if let Some(explanation) =
+        lexpr::explain_at_with_options(&document.text, offset, options.clone())
+    {
+        value.push_str(&format!(
+            "\n\n`{}`: {}",
+            explanation.sexp.stringify(),
+            explanation.rule
+        ));
+    }
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(document.range(sexp.span())),
+    })
+}
+
+/// Classifies every token: the words of a merged name are functions, or
+/// operators for symbolic words, names passed as arguments are variables.
+/// Tokens spanning several lines are left to the client's grammar.
+pub(crate) fn semantic_tokens(document: &Document, options: &ParseOptions) -> Vec<SemanticToken> {
+    let (sexps, _) = lexpr::parse_str_recovering(&document.text, options.clone());
+    let mut names = vec![];
+    for sexp in &sexps {
+        collect_names(sexp, false, &mut names);
+    }
+
+    let mut tokens = vec![];
+    let mut previous = lsp_types::Position::new(0, 0);
+    let mut tokenizer = Tokenizer::new(&document.text);
+    loop {
+        let token = match tokenizer.next_token() {
+            Ok(Some(token)) => token,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        let span = token.span;
+        if span.start.line_number != span.end.line_number {
+            continue;
+        }
+        let token_type = match token.kind {
+            TokenKind::Identifier(_) | TokenKind::Operator(_) => {
+                let innermost = names
+                    .iter()
+                    .filter(|(name_span, _)| {
+                        name_span.start <= span.start && span.start < name_span.end
+                    })
+                    .min_by_key(|(name_span, _)| name_span.len());
+                match innermost {
+                    Some((_, false)) => VARIABLE,
+                    Some((_, true)) if matches!(token.kind, TokenKind::Operator(_)) => OPERATOR,
+                    Some((_, true)) => FUNCTION,
+                    None => continue,
+                }
+            }
+            TokenKind::NumberLiteral { .. } => NUMBER,
+            TokenKind::StringLiteral(_) => STRING,
+            TokenKind::LineComment(_) | TokenKind::BlockComment(_) => COMMENT,
+            _ => continue,
+        };
+        let start = document.position(span.start.byte_index);
+        tokens.push(SemanticToken {
+            delta_line: start.line - previous.line,
+            delta_start: if start.line == previous.line {
+                start.character - previous.character
+            } else {
+                start.character
+            },
+            length: span.slice(&document.text).encode_utf16().count() as u32,
+            token_type,
+            token_modifiers_bitset: 0,
+        });
+        previous = start;
+    }
+    tokens
+}
+
+/// The span of every name in `sexp`, and whether it heads a call.
+fn collect_names(sexp: &Sexp, is_head: bool, names: &mut Vec<(Span, bool)>) {
+    match sexp {
+        Sexp::Name(name) => names.push((name.span(), is_head)),
+        Sexp::List(list) => {
+            for (index, expr) in list.exprs().iter().enumerate() {
+                collect_names(expr, index == 0, names);
+            }
+        }
+        Sexp::Vector(vector) => {
+            for expr in vector.exprs() {
+                collect_names(expr, false, names);
+            }
+        }
+        Sexp::Map(map) => {
+            for (key, value) in map.entries() {
+                collect_names(key, false, names);
+                collect_names(value, false, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parenthesized expressions, forms and colon blocks that span several
+/// lines, one range per starting line.
+pub(crate) fn folding_ranges(document: &Document, options: &ParseOptions) -> Vec<FoldingRange> {
+    let (cst, _) = parse_cst(&document.text, options.clone());
+    let mut ranges: Vec<FoldingRange> = vec![];
+    let mut nodes = cst
+        .root
+        .child_nodes()
+        .map(|form| (form, true))
+        .collect::<Vec<_>>();
+    nodes.reverse();
+    while let Some((node, is_form)) = nodes.pop() {
+        let start_line = node.span.start.line_number as u32 - 1;
+        let end_line = node.span.end.line_number as u32 - 1;
+        let is_block =
+            is_form || matches!(node.kind, SyntaxKind::Parenthesized | SyntaxKind::Colon);
+        if is_block
+            && end_line > start_line
+            && ranges.iter().all(|range| range.start_line != start_line)
+        {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                ..Default::default()
+            });
+        }
+        let children = node.child_nodes().collect::<Vec<_>>();
+        nodes.extend(children.into_iter().rev().map(|child| (child, false)));
+    }
+    ranges.sort_by_key(|range| range.start_line);
+    ranges
+}
+
+/// One edit replacing the whole document with its formatted text, nothing
+/// for a document that does not parse.
+pub(crate) fn formatting(
+    document: &Document,
+    options: &ParseOptions,
+) -> Result<Option<Vec<TextEdit>>, String> {
+    match lexpr::format_str(&document.text, options, &FormatOptions::default()) {
+        Ok(formatted) if formatted == document.text => Ok(Some(vec![])),
+        Ok(formatted) => Ok(Some(vec![TextEdit::new(document.full_range(), formatted)])),
+        Err(FormatError::Parse(_)) => Ok(None),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_types(text: &str) -> Vec<(String, u32)> {
+        let document = Document::new(text.to_string());
+        let mut line = 0;
+        let mut character = 0;
+        semantic_tokens(&document, &ParseOptions::default())
+            .into_iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    character = 0;
+                }
+                line += token.delta_line;
+                character += token.delta_start;
+                let start = document
+                    .offset(lsp_types::Position::new(line, character))
+                    .unwrap();
+                let end = document
+                    .offset(lsp_types::Position::new(line, character + token.length))
+                    .unwrap();
+                (document.text[start..end].to_string(), token.token_type)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tells_name_words_from_arguments() {
+        let tokens = token_types("x. greater than: y,\n# 𝄞 note\nif (n = 0) then \"é\" else: 1");
+        assert_eq!(
+            tokens,
+            [
+                ("x", VARIABLE),
+                ("greater", FUNCTION),
+                ("than", FUNCTION),
+                ("y", VARIABLE),
+                ("# 𝄞 note", COMMENT),
+                ("if", FUNCTION),
+                ("n", VARIABLE),
+                ("=", OPERATOR),
+                ("0", NUMBER),
+                ("then", FUNCTION),
+                ("\"é\"", STRING),
+                ("else", FUNCTION),
+                ("1", NUMBER),
+            ]
+            .map(|(text, token_type)| (text.to_string(), token_type))
+        );
+    }
+
+    #[test]
+    fn folds_multi_line_blocks() {
+        let document = Document::new(
+            "def (n. fib):\n  if (n = 0)\n  then 0\n  else: (n -\n    1)".to_string(),
+        );
+        let ranges = folding_ranges(&document, &ParseOptions::default())
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(0, 4), (1, 4), (3, 4)]);
+    }
+}
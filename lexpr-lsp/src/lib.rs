@@ -0,0 +1,368 @@
+//! A Language Server Protocol server for Lexpr: diagnostics from the parser,
+//! hover with the S-expression a form translates to, semantic tokens, folding
+//! ranges and document formatting.
+
+mod document;
+mod features;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lexpr::ParseOptions;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        FoldingRangeRequest, Formatting, HoverRequest, Request as _, SemanticTokensFullRequest,
+    },
+    FoldingRangeProviderCapability, HoverProviderCapability, OneOf, PublishDiagnosticsParams,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+use crate::document::Document;
+
+pub fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: features::TOKEN_TYPES.to_vec(),
+                    token_modifiers: vec![],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+/// Serves `connection` until the client shuts the server down.
+///
+/// The parser options come from the initialization options of the client,
+/// `{"layout": true, "newlineAsComma": true}` turns both layout modes on.
+pub fn run(connection: &Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let initialize_params = connection.initialize(serde_json::to_value(server_capabilities())?)?;
+    let mut server = Server {
+        options: parse_options(&initialize_params["initializationOptions"]),
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                connection
+                    .sender
+                    .send(Message::Response(server.handle_request(request)))?;
+            }
+            Message::Notification(notification) => {
+                for notification in server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_options(initialization_options: &serde_json::Value) -> ParseOptions {
+    let flag = |name: &str| initialization_options[name].as_bool().unwrap_or(false);
+    ParseOptions {
+        layout: flag("layout"),
+        newline_as_comma: flag("newlineAsComma"),
+        ..ParseOptions::default()
+    }
+}
+
+struct Server {
+    options: ParseOptions,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, |server, params| {
+                let position = params.text_document_position_params;
+                Ok(server
+                    .document(&position.text_document.uri)
+                    .and_then(|document| {
+                        let offset = document.offset(position.position)?;
+                        features::hover(document, &server.options, offset)
+                    }))
+            }),
+            SemanticTokensFullRequest::METHOD => {
+                self.respond::<SemanticTokensFullRequest>(request, |server, params| {
+                    Ok(server.document(&params.text_document.uri).map(|document| {
+                        SemanticTokensResult::Tokens(SemanticTokens {
+                            result_id: None,
+                            data: features::semantic_tokens(document, &server.options),
+                        })
+                    }))
+                })
+            }
+            FoldingRangeRequest::METHOD => {
+                self.respond::<FoldingRangeRequest>(request, |server, params| {
+                    Ok(server
+                        .document(&params.text_document.uri)
+                        .map(|document| features::folding_ranges(document, &server.options)))
+                })
+            }
+            Formatting::METHOD => {
+                self.respond::<Formatting>(request, |server, params| {
+                    match server.document(&params.text_document.uri) {
+                        Some(document) => features::formatting(document, &server.options),
+                        None => Ok(None),
+                    }
+                })
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            ),
+        }
+    }
+
+    /// Answers `request` with `handler`, an `Err` is reported to the client
+    /// as an internal error.
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> Result<R::Result, String>,
+    ) -> Response {
+        let params = match serde_json::from_value(request.params) {
+            Ok(params) => params,
+            Err(error) => {
+                return Response::new_err(
+                    request.id,
+                    ErrorCode::InvalidParams as i32,
+                    error.to_string(),
+                )
+            }
+        };
+        match handler(self, params) {
+            Ok(result) => Response::new_ok(request.id, result),
+            Err(message) => Response::new_err(request.id, ErrorCode::InternalError as i32, message),
+        }
+    }
+
+    /// Updates the open documents, returning the diagnostics to publish.
+    fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let (uri, version) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Ok(params) = notification
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                else {
+                    return vec![];
+                };
+                let text_document = params.text_document;
+                self.documents
+                    .insert(text_document.uri.clone(), Document::new(text_document.text));
+                (text_document.uri, Some(text_document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let Ok(mut params) = notification
+                    .extract::<lsp_types::DidChangeTextDocumentParams>(
+                        DidChangeTextDocument::METHOD,
+                    )
+                else {
+                    return vec![];
+                };
+                // Changes are synced in full, the last one is the new text.
+                let Some(change) = params.content_changes.pop() else {
+                    return vec![];
+                };
+                let text_document = params.text_document;
+                self.documents
+                    .insert(text_document.uri.clone(), Document::new(change.text));
+                (text_document.uri, Some(text_document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let Ok(params) = notification
+                    .extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                else {
+                    return vec![];
+                };
+                self.documents.remove(&params.text_document.uri);
+                (params.text_document.uri, None)
+            }
+            _ => return vec![],
+        };
+        let diagnostics = self
+            .document(&uri)
+            .map(|document| features::diagnostics(document, &self.options))
+            .unwrap_or_default();
+        vec![Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version,
+            },
+        )]
+    }
+
+    fn document(&self, uri: &Url) -> Option<&Document> {
+        self.documents.get(uri)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{
+        notification::Initialized, request::Initialize, request::Shutdown,
+        DidOpenTextDocumentParams, DocumentFormattingParams, FoldingRangeParams, FormattingOptions,
+        HoverContents, HoverParams, InitializeParams, Position, SemanticTokensParams,
+        TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// A scripted client, talking to a server running on another thread.
+    struct Client {
+        connection: Connection,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> Response {
+            self.next_id += 1;
+            let request = Request::new(self.next_id.into(), R::METHOD.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) => response,
+                message => panic!("expected the response to {}, got {:?}", R::METHOD, message),
+            }
+        }
+
+        fn result<R: lsp_types::request::Request>(&mut self, params: R::Params) -> R::Result {
+            let response = self.request::<R>(params);
+            serde_json::from_value(response.result.expect("no result")).unwrap()
+        }
+
+        fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+            let notification = Notification::new(N::METHOD.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        fn published_diagnostics(&self) -> PublishDiagnosticsParams {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification) => {
+                    notification.extract(PublishDiagnostics::METHOD).unwrap()
+                }
+                message => panic!("expected diagnostics, got {:?}", message),
+            }
+        }
+    }
+
+    #[test]
+    fn serves_a_scripted_session() {
+        let (server_connection, connection) = Connection::memory();
+        let server = std::thread::spawn(move || run(&server_connection).unwrap());
+        let mut client = Client {
+            connection,
+            next_id: 0,
+        };
+        let initialize = client.result::<Initialize>(InitializeParams {
+            initialization_options: Some(json!({"layout": true, "newlineAsComma": true})),
+            ..Default::default()
+        });
+        assert!(initialize.capabilities.hover_provider.is_some());
+        client.notify::<Initialized>(lsp_types::InitializedParams {});
+
+        let uri = Url::parse("file:///example.lexpr").unwrap();
+        let document = TextDocumentIdentifier::new(uri.clone());
+        client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "lexpr".to_string(),
+                1,
+                "é. greater than: y\nf (".to_string(),
+            ),
+        });
+        let published = client.published_diagnostics();
+        assert_eq!(published.version, Some(1));
+        assert_eq!(published.diagnostics.len(), 1);
+        assert_eq!(published.diagnostics[0].range.start, Position::new(1, 3));
+
+        let hover = client
+            .result::<HoverRequest>(HoverParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    document.clone(),
+                    Position::new(0, 5),
+                ),
+                work_done_progress_params: Default::default(),
+            })
+            .unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown");
+        };
+        assert_eq!(
+            markup.value,
+            format!(
+                "```scheme\n(greater-than é y)\n```\n\n`greater-than`: {}",
+                lexpr::Rule::Merge
+            )
+        );
+        assert_eq!(hover.range.unwrap().end, Position::new(0, 18));
+
+        let Some(SemanticTokensResult::Tokens(tokens)) = client
+            .result::<SemanticTokensFullRequest>(SemanticTokensParams {
+                text_document: document.clone(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+        else {
+            panic!("expected semantic tokens");
+        };
+        // `f` of the form that failed to parse is left unclassified.
+        assert_eq!(tokens.data.len(), 4);
+        assert_eq!((tokens.data[0].length, tokens.data[1].delta_start), (1, 3));
+
+        let folding_ranges = client.result::<FoldingRangeRequest>(FoldingRangeParams {
+            text_document: document.clone(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        assert_eq!(folding_ranges, Some(vec![]));
+
+        client.notify::<DidChangeTextDocument>(lsp_types::DidChangeTextDocumentParams {
+            text_document: lsp_types::VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+            content_changes: vec![lsp_types::TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "x .f".to_string(),
+            }],
+        });
+        assert!(client.published_diagnostics().diagnostics.is_empty());
+        let edits = client
+            .result::<Formatting>(DocumentFormattingParams {
+                text_document: document,
+                options: FormattingOptions::default(),
+                work_done_progress_params: Default::default(),
+            })
+            .unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "x. f\n");
+
+        assert!(client.request::<Shutdown>(()).error.is_none());
+        client.notify::<lsp_types::notification::Exit>(());
+        server.join().unwrap();
+    }
+}
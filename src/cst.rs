@@ -0,0 +1,380 @@
+//! A lossless concrete syntax tree: every character of the input belongs to
+//! a token or to the trivia around one, so printing the tree gives back the
+//! input byte for byte.
+
+use crate::{
+    parse::{parse_node_shapes, ParseError, ParseOptions},
+    tokenizer::{Position, Span, Token, TokenKind, Tokenizer},
+};
+
+/// Parses `input_text` into a [`Cst`], recovering from errors like
+/// [`crate::parse_str_recovering`], so a tree is built for any input.
+pub fn parse_cst(input_text: &str, options: ParseOptions) -> (Cst, Vec<ParseError>) {
+    let (shapes, errors) = parse_node_shapes(input_text, options);
+    let (tokens, end_trivia, end) = tokenize_with_trivia(input_text);
+    let start = Position {
+        line_number: 1,
+        column_number: 1,
+        character_index: 0,
+        byte_index: 0,
+    };
+    let root = NodeShape::new(SyntaxKind::Document, Span { start, end }, shapes)
+        .build(&mut tokens.into_iter().peekable());
+    (Cst { root, end_trivia }, errors)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// The whole input, forms separated by commas.
+    Document,
+    /// `left: right`
+    Colon,
+    /// `left. right`
+    Dot,
+    /// Operators merged with their operands, like `x + y`.
+    OperatorCall,
+    /// Adjacent atomic expressions merged into one call, like `f x y`.
+    Call,
+    /// A list in `(...)`, `[...]` or `{...}`, with its brackets and commas.
+    Parenthesized,
+    /// A single name, operator, string or number.
+    Atom,
+    /// A form that failed to parse, holding the tokens skipped by recovery.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub root: CstNode,
+    /// Trivia from the line after the last token up to the end of input.
+    pub end_trivia: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: SyntaxKind,
+    pub span: Span,
+    pub children: Vec<CstElement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    /// The source text of the token.
+    pub text: String,
+    pub span: Span,
+    /// Trivia from the line of the previous token's end up to this token.
+    pub leading_trivia: Vec<Trivia>,
+    /// Trivia after this token up to the end of its line.
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    /// Text the tokenizer could not read, like a string with an invalid escape.
+    Skipped,
+}
+
+impl CstNode {
+    /// Every token under this node, in source order.
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &CstToken> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            CstElement::Node(node) => node.tokens(),
+            CstElement::Token(token) => Box::new(Some(token).into_iter()),
+        }))
+    }
+
+    /// The nodes directly under this node.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &CstNode> {
+        self.children.iter().filter_map(|child| match child {
+            CstElement::Node(node) => Some(node),
+            CstElement::Token(_) => None,
+        })
+    }
+}
+
+impl std::fmt::Display for Cst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.root)?;
+        self.end_trivia
+            .iter()
+            .try_for_each(|trivia| write!(f, "{}", trivia.text))
+    }
+}
+
+impl std::fmt::Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.children.iter().try_for_each(|child| match child {
+            CstElement::Node(node) => write!(f, "{}", node),
+            CstElement::Token(token) => write!(f, "{}", token),
+        })
+    }
+}
+
+impl std::fmt::Display for CstToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.leading_trivia
+            .iter()
+            .try_for_each(|trivia| write!(f, "{}", trivia.text))?;
+        write!(f, "{}", self.text)?;
+        self.trailing_trivia
+            .iter()
+            .try_for_each(|trivia| write!(f, "{}", trivia.text))
+    }
+}
+
+/// The kind and span of a node of the parse tree, without its tokens.
+#[derive(Debug)]
+pub(crate) struct NodeShape {
+    kind: SyntaxKind,
+    span: Span,
+    children: Vec<NodeShape>,
+}
+
+impl NodeShape {
+    pub(crate) fn new(kind: SyntaxKind, span: Span, children: Vec<NodeShape>) -> Self {
+        Self {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    /// Takes the tokens within the span of this node, the tokens of each
+    /// child go to that child, the rest (brackets, commas, dots and colons)
+    /// to this node.
+    fn build(self, tokens: &mut std::iter::Peekable<std::vec::IntoIter<CstToken>>) -> CstNode {
+        let mut children = vec![];
+        let mut shapes = self.children.into_iter().peekable();
+        while let Some(token) = tokens.peek() {
+            let token_start = token.span.start.character_index;
+            if token_start >= self.span.end.character_index {
+                break;
+            }
+            match shapes.next_if(|shape| shape.span.start.character_index <= token_start) {
+                Some(shape) => children.push(CstElement::Node(shape.build(tokens))),
+                None => children.extend(tokens.next().map(CstElement::Token)),
+            }
+        }
+        // Forms cut short at the end of input are empty error nodes.
+        children.extend(shapes.map(|shape| CstElement::Node(shape.build(tokens))));
+        CstNode {
+            kind: self.kind,
+            span: self.span,
+            children,
+        }
+    }
+}
+
+/// Every token of `input_text` with its trivia, the trivia after the last
+/// token's line, and the end of input.
+fn tokenize_with_trivia(input_text: &str) -> (Vec<CstToken>, Vec<Trivia>, Position) {
+    let mut tokenizer = Tokenizer::new(input_text);
+    let mut collector = TokenCollector::default();
+    let mut last_end = 0;
+    loop {
+        let token = match tokenizer.next_token() {
+            Ok(token) => token,
+            // The characters of a malformed token show up as skipped trivia.
+            Err(_) => continue,
+        };
+        let start = token
+            .as_ref()
+            .map_or(tokenizer.position(), |token| token.span.start)
+            .byte_index;
+        for trivia in split_whitespace_runs(&input_text[last_end..start]) {
+            collector.push_trivia(trivia);
+        }
+        let Some(Token { kind, span }) = token else {
+            break;
+        };
+        last_end = span.end.byte_index;
+        let token_text = input_text[span.start.byte_index..last_end].to_string();
+        match kind {
+            TokenKind::LineComment(_) => collector.push_trivia(Trivia {
+                kind: TriviaKind::LineComment,
+                text: token_text,
+            }),
+            TokenKind::BlockComment(_) => collector.push_trivia(Trivia {
+                kind: TriviaKind::BlockComment,
+                text: token_text,
+            }),
+            kind => collector.push_token(kind, token_text, span),
+        }
+    }
+    (
+        collector.tokens,
+        collector.pending_trivia,
+        tokenizer.position(),
+    )
+}
+
+#[derive(Default)]
+struct TokenCollector {
+    tokens: Vec<CstToken>,
+    /// Trivia waiting for the next token.
+    pending_trivia: Vec<Trivia>,
+    /// Whether trivia still sits on the line of the last token.
+    on_token_line: bool,
+}
+
+impl TokenCollector {
+    fn push_token(&mut self, kind: TokenKind, text: String, span: Span) {
+        self.tokens.push(CstToken {
+            kind,
+            text,
+            span,
+            leading_trivia: std::mem::take(&mut self.pending_trivia),
+            trailing_trivia: vec![],
+        });
+        self.on_token_line = true;
+    }
+
+    /// Trivia on the line of the last token trails it, a newline ends that
+    /// line and the trivia from there leads the next token.
+    fn push_trivia(&mut self, trivia: Trivia) {
+        let Some(last_token) = self.tokens.last_mut().filter(|_| self.on_token_line) else {
+            self.pending_trivia.push(trivia);
+            return;
+        };
+        match trivia.text.find('\n') {
+            Some(newline) if trivia.kind == TriviaKind::Whitespace => {
+                let (same_line, next_lines) = trivia.text.split_at(newline);
+                if !same_line.is_empty() {
+                    last_token.trailing_trivia.push(whitespace(same_line));
+                }
+                self.pending_trivia.push(whitespace(next_lines));
+                self.on_token_line = false;
+            }
+            _ => last_token.trailing_trivia.push(trivia),
+        }
+    }
+}
+
+fn whitespace(text: &str) -> Trivia {
+    Trivia {
+        kind: TriviaKind::Whitespace,
+        text: text.to_string(),
+    }
+}
+
+/// Splits text between tokens into whitespace and skipped trivia.
+fn split_whitespace_runs(text: &str) -> Vec<Trivia> {
+    let mut runs: Vec<Trivia> = vec![];
+    for character in text.chars() {
+        let kind = if character.is_whitespace() {
+            TriviaKind::Whitespace
+        } else {
+            TriviaKind::Skipped
+        };
+        match runs.last_mut() {
+            Some(run) if run.kind == kind => run.text.push(character),
+            _ => runs.push(Trivia {
+                kind,
+                text: character.to_string(),
+            }),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cst(input: &str) -> Cst {
+        parse_cst(input, ParseOptions::default()).0
+    }
+
+    /// The tree as nested kinds, with tokens as their text.
+    fn outline(node: &CstNode) -> String {
+        let children = node
+            .children
+            .iter()
+            .map(|child| match child {
+                CstElement::Node(node) => outline(node),
+                CstElement::Token(token) => token.text.clone(),
+            })
+            .collect::<Vec<_>>();
+        format!("{:?}[{}]", node.kind, children.join(" "))
+    }
+
+    #[test]
+    fn reproduces_input() {
+        let layout = ParseOptions {
+            layout: true,
+            newline_as_comma: true,
+            ..Default::default()
+        };
+        for input in [
+            "",
+            "  # only a comment\n",
+            "x. greater than: y",
+            "def ((n: int) .factorial):\n    if (n < 2) then\n        1\n    else:\n        n *: n - 1 .!\n",
+            "a, #| block #| nested |# |# b\r\n\tc # trailing\n\n",
+            "f (x, ), s \"a\\qb\" t, g (",
+            "héllo wörld . ☃ ,\n]",
+        ] {
+            assert_eq!(cst(input).to_string(), input);
+            let (cst, _) = parse_cst(input, layout.clone());
+            assert_eq!(cst.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn groups_tokens_by_rule() {
+        assert_eq!(
+            outline(&cst("x. f: (y, z w)").root),
+            "Document[Colon[Dot[Atom[x] . Atom[f]] : Parenthesized[( Atom[y] , Call[Atom[z] Atom[w]] )]]]"
+        );
+        assert_eq!(
+            outline(&cst("a + b, .").root),
+            "Document[OperatorCall[Atom[a] Atom[+] Atom[b]] , Error[.]]"
+        );
+    }
+
+    #[test]
+    fn attaches_trivia() {
+        let cst = cst("# head\nx # tail\n  .f\n# end\n");
+        let tokens = cst.root.tokens().collect::<Vec<_>>();
+        assert_eq!(
+            tokens[0]
+                .leading_trivia
+                .iter()
+                .map(|trivia| trivia.kind)
+                .collect::<Vec<_>>(),
+            [TriviaKind::LineComment, TriviaKind::Whitespace]
+        );
+        assert_eq!(
+            tokens[0]
+                .trailing_trivia
+                .iter()
+                .map(|trivia| trivia.text.as_str())
+                .collect::<Vec<_>>(),
+            [" ", "# tail"]
+        );
+        assert_eq!(tokens[1].leading_trivia, [whitespace("\n  ")]);
+        assert_eq!(
+            cst.end_trivia
+                .iter()
+                .map(|trivia| trivia.text.as_str())
+                .collect::<Vec<_>>(),
+            ["\n", "# end", "\n"]
+        );
+    }
+}
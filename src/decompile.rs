@@ -0,0 +1,489 @@
+use crate::{
+    number::Number,
+    parse::parse_str,
+    sexp::{LiteralName, Sexp},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecompileError {
+    /// No Lexpr translates to this S-expression, like a call of a single word
+    /// without arguments, which Lexpr reads as the bare name.
+    Unrepresentable { sexp: String, reason: &'static str },
+    /// Every printed form parsed back to a different S-expression, which is a
+    /// bug of the decompiler.
+    RoundTrip { sexp: String, lexpr: String },
+}
+
+impl std::fmt::Display for DecompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecompileError::Unrepresentable { sexp, reason } => {
+                write!(f, "`{}` has no Lexpr form: {}", sexp, reason)
+            }
+            DecompileError::RoundTrip { sexp, lexpr } => {
+                write!(f, "`{}` printed as `{}` does not read back", sexp, lexpr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecompileError {}
+
+/// Prints `sexp` as the Lexpr that [`parse_str`] reads back to it, with the
+/// default hyphen naming, so `(greater-than x y)` is `x. greater than: y`.
+///
+/// Arguments go back where the [`crate::MixfixName`] of a parsed call says
+/// they sat, passed with a dot before the words and with a colon after them,
+/// and the output falls back to parentheses only when that does not read
+/// back.
+pub fn decompile(sexp: &Sexp) -> Result<String, DecompileError> {
+    let expected = sexp.stringify();
+    let mut lexpr = String::new();
+    for style in [Style::Idiomatic, Style::Parenthesized] {
+        lexpr = style.expr(sexp)?.text;
+        let reads_back = parse_str(&lexpr)
+            .is_ok_and(|sexps| matches!(sexps.as_slice(), [read] if read.stringify() == expected));
+        if reads_back {
+            return Ok(lexpr);
+        }
+    }
+    Err(DecompileError::RoundTrip {
+        sexp: expected,
+        lexpr,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Style {
+    Idiomatic,
+    Parenthesized,
+}
+
+/// The loosest rule at the top of printed Lexpr, which decides where it
+/// needs parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    /// A literal or a parenthesized list.
+    Atomic,
+    /// A bare word, which merges with the words next to it.
+    Word,
+    /// Merged words and operators with their arguments.
+    Call,
+    Dot,
+    Colon,
+}
+
+struct Printed {
+    text: String,
+    level: Level,
+}
+
+impl Printed {
+    fn new(text: String, level: Level) -> Self {
+        Self { text, level }
+    }
+
+    /// Wrapped in parentheses when looser than `level`.
+    fn at(self, level: Level) -> String {
+        if self.level <= level {
+            self.text
+        } else {
+            format!("({})", self.text)
+        }
+    }
+}
+
+impl Style {
+    fn expr(self, sexp: &Sexp) -> Result<Printed, DecompileError> {
+        match sexp {
+            Sexp::Number(number) => {
+                if matches!(number.value(), Number::Float(value) if !value.is_finite()) {
+                    return Err(unrepresentable(sexp, "only finite numbers have literals"));
+                }
+                // The literal as written, like `0xff` or `1_000`, when it
+                // reads back as the value.
+                let text = match Number::from_literal(number.text()) {
+                    Ok(value) if &value == number.value() => number.text().to_string(),
+                    _ => number.value().to_string(),
+                };
+                Ok(Printed::new(text, Level::Atomic))
+            }
+            Sexp::String(string) => Ok(Printed::new(string_literal(string.value()), Level::Atomic)),
+            Sexp::Name(name) if is_word(name.value()) || is_operator(name.value()) => {
+                Ok(Printed::new(name.value().to_string(), Level::Word))
+            }
+            Sexp::Name(_) => Err(unrepresentable(
+                sexp,
+                "a bare name is a single word or operator, merged words always form a call",
+            )),
+            Sexp::List(list) => self.call(sexp, list.exprs()),
+            Sexp::Vector(vector) => {
+                let elements = vector
+                    .exprs()
+                    .iter()
+                    .map(|element| Ok(self.expr(element)?.text))
+                    .collect::<Result<Vec<_>, DecompileError>>()?;
+                Ok(Printed::new(
+                    format!("[{}]", elements.join(", ")),
+                    Level::Atomic,
+                ))
+            }
+            // A key looser than a dot would take the value into its colon.
+            Sexp::Map(map) => {
+                let entries = map
+                    .entries()
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok(format!(
+                            "{}: {}",
+                            self.expr(key)?.at(Level::Dot),
+                            self.expr(value)?.text
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, DecompileError>>()?;
+                Ok(Printed::new(
+                    format!("{{{}}}", entries.join(", ")),
+                    Level::Atomic,
+                ))
+            }
+            Sexp::Error(_) => Err(unrepresentable(
+                sexp,
+                "it stands for a form that failed to parse",
+            )),
+        }
+    }
+
+    fn call(self, sexp: &Sexp, exprs: &[Sexp]) -> Result<Printed, DecompileError> {
+        let Some((head, arguments)) = exprs.split_first() else {
+            return Err(unrepresentable(
+                sexp,
+                "brackets need at least one expression",
+            ));
+        };
+        let named = match head {
+            Sexp::Name(name) => words(name).map(|words| (name, words)),
+            _ => None,
+        };
+        if let Some((Sexp::Name(last), rest)) = arguments.split_last() {
+            if let Some(words) = words(last).filter(|words| words.len() > 1) {
+                return self.headed_call(head, rest, &words);
+            }
+        }
+        match (named, arguments) {
+            (Some((_, words)), []) if words.len() > 1 => {
+                Ok(Printed::new(words.join(" "), Level::Call))
+            }
+            (_, []) => Err(unrepresentable(
+                sexp,
+                "a call of one word without arguments reads as the bare word",
+            )),
+            (Some((name, words)), arguments) if words.len() == 1 && is_operator(&words[0]) => {
+                self.operator_call(name, &words[0], arguments)
+            }
+            (Some((name, words)), arguments) if is_operator(&words[0]) => {
+                self.operator_chain(name, &words, arguments)
+            }
+            (Some((name, words)), arguments) => self.words_call(name, &words, arguments),
+            // `(f) (x)` is `(f x)`, the first argument heads a call without words.
+            (None, _) => Ok(Printed::new(
+                exprs
+                    .iter()
+                    .map(|expr| self.argument(expr))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(" "),
+                Level::Call,
+            )),
+        }
+    }
+
+    /// Arguments sit where the mixfix name of the head says, or else the
+    /// first one, when a name, before the words and the rest after them.
+    /// With more than two arguments they all go after the words, which stay
+    /// together as in `if then else (c) (a): b`. A first argument before the
+    /// words is passed with a dot and a last argument after them with a
+    /// colon, unless they are literals.
+    fn words_call(
+        self,
+        head: &LiteralName,
+        words: &[String],
+        arguments: &[Sexp],
+    ) -> Result<Printed, DecompileError> {
+        let holes = match (self, head.mixfix()) {
+            (Style::Idiomatic, Some(mixfix))
+                if mixfix.parts == words && mixfix.holes.len() == arguments.len() =>
+            {
+                mixfix.holes.clone()
+            }
+            (Style::Idiomatic, _) if arguments.len() > 2 => vec![words.len(); arguments.len()],
+            (Style::Idiomatic, _) => arguments
+                .iter()
+                .enumerate()
+                .map(|(index, argument)| match argument {
+                    Sexp::Name(_) if index == 0 => 0,
+                    _ => words.len(),
+                })
+                .collect(),
+            (Style::Parenthesized, _) => vec![words.len(); arguments.len()],
+        };
+        let mut placed = holes.into_iter().zip(arguments).collect::<Vec<_>>();
+        let mut dotted = None;
+        let mut coloned = None;
+        if let Style::Idiomatic = self {
+            if let Some(&(0, argument)) =
+                placed.first().filter(|(_, argument)| !is_literal(argument))
+            {
+                dotted = Some(self.expr(argument)?.at(Level::Dot));
+                placed.remove(0);
+            }
+            if let Some(&(hole, argument)) = placed.last() {
+                if hole == words.len() && !is_literal(argument) {
+                    coloned = Some(self.expr(argument)?);
+                    placed.pop();
+                }
+            }
+        }
+        let mut call = vec![];
+        let mut placed = placed.into_iter().peekable();
+        for index in 0..=words.len() {
+            while let Some((_, argument)) = placed.next_if(|&(hole, _)| hole <= index) {
+                call.push(self.argument(argument)?);
+            }
+            call.extend(words.get(index).cloned());
+        }
+        let call = call.join(" ");
+        let (call, level) = match dotted {
+            Some(dotted) => (format!("{}. {}", dotted, call), Level::Dot),
+            None => (call, Level::Call),
+        };
+        Ok(match coloned {
+            Some(coloned) => Printed::new(format!("{}: {}", call, coloned.text), Level::Colon),
+            None => Printed::new(call, level),
+        })
+    }
+
+    /// A call whose last argument merges several words, which only a call
+    /// headed by parentheses or a colon passes, as in `(f) (x) y z` and
+    /// `f: y z`.
+    fn headed_call(
+        self,
+        head: &Sexp,
+        arguments: &[Sexp],
+        words: &[String],
+    ) -> Result<Printed, DecompileError> {
+        let head = self.expr(head)?;
+        if let (Level::Word, []) = (head.level, arguments) {
+            return Ok(Printed::new(
+                format!("{}: {}", head.text, words.join(" ")),
+                Level::Colon,
+            ));
+        }
+        Ok(Printed::new(
+            Some(format!("({})", head.text))
+                .into_iter()
+                .map(Ok)
+                .chain(arguments.iter().map(|argument| self.argument(argument)))
+                .chain(words.iter().cloned().map(Ok))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" "),
+            Level::Call,
+        ))
+    }
+
+    /// `- x`, `x !` and `x + y`, with more arguments the operator heads a
+    /// call without words, as in `(+) (x) (y) (z)`.
+    fn operator_call(
+        self,
+        head: &LiteralName,
+        operator: &str,
+        arguments: &[Sexp],
+    ) -> Result<Printed, DecompileError> {
+        let operand = |argument: &Sexp| -> Result<String, DecompileError> {
+            let printed = self.expr(argument)?;
+            Ok(match (argument, self) {
+                (Sexp::Name(name), Style::Idiomatic) if is_word(name.value()) => printed.text,
+                _ => printed.at(Level::Atomic),
+            })
+        };
+        let postfix = matches!(self, Style::Idiomatic)
+            && head.mixfix().is_some_and(|mixfix| mixfix.holes == [0]);
+        let text = match arguments {
+            [operand_1] if postfix => format!("{} {}", operand(operand_1)?, operator),
+            [operand_1] => format!("{} {}", operator, operand(operand_1)?),
+            [left, right] => format!("{} {} {}", operand(left)?, operator, operand(right)?),
+            arguments => Some(format!("({})", operator))
+                .into_iter()
+                .map(Ok)
+                .chain(arguments.iter().map(|argument| self.argument(argument)))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" "),
+        };
+        Ok(Printed::new(text, Level::Call))
+    }
+
+    /// `a + b * c` and `x - - y`, with the operands where the mixfix name
+    /// of the head says.
+    fn operator_chain(
+        self,
+        head: &LiteralName,
+        operators: &[String],
+        arguments: &[Sexp],
+    ) -> Result<Printed, DecompileError> {
+        let holes = match (self, head.mixfix()) {
+            (Style::Idiomatic, Some(mixfix))
+                if mixfix.parts == operators && mixfix.holes.len() == arguments.len() =>
+            {
+                mixfix.holes.clone()
+            }
+            _ => return self.words_call(head, operators, arguments),
+        };
+        let mut chain = vec![];
+        let mut placed = holes.into_iter().zip(arguments).peekable();
+        for index in 0..=operators.len() {
+            while let Some((_, argument)) = placed.next_if(|&(hole, _)| hole <= index) {
+                let printed = self.expr(argument)?;
+                chain.push(match argument {
+                    Sexp::Name(name) if is_word(name.value()) => printed.text,
+                    _ => printed.at(Level::Atomic),
+                });
+            }
+            chain.extend(operators.get(index).cloned());
+        }
+        Ok(Printed::new(chain.join(" "), Level::Call))
+    }
+
+    /// An argument among words, which must not merge with them.
+    fn argument(self, sexp: &Sexp) -> Result<String, DecompileError> {
+        Ok(self.expr(sexp)?.at(Level::Atomic))
+    }
+}
+
+fn unrepresentable(sexp: &Sexp, reason: &'static str) -> DecompileError {
+    DecompileError::Unrepresentable {
+        sexp: sexp.stringify(),
+        reason,
+    }
+}
+
+fn is_literal(sexp: &Sexp) -> bool {
+    matches!(
+        sexp,
+        Sexp::Number(_) | Sexp::String(_) | Sexp::Vector(_) | Sexp::Map(_)
+    )
+}
+
+/// The words a call name merges from, operators joined by dots or
+/// alphanumeric words joined by hyphens.
+fn words(name: &LiteralName) -> Option<Vec<String>> {
+    let name = name.value();
+    let operators = name.split('.').map(str::to_string).collect::<Vec<_>>();
+    if operators.iter().all(|operator| is_operator(operator)) {
+        return Some(operators);
+    }
+    let words = name.split('-').map(str::to_string).collect::<Vec<_>>();
+    words.iter().all(|word| is_word(word)).then_some(words)
+}
+
+/// Whether `text` reads as one identifier token.
+fn is_word(text: &str) -> bool {
+    let mut characters = text.chars();
+    characters
+        .next()
+        .is_some_and(|first| (first.is_alphanumeric() || first == '_') && !first.is_ascii_digit())
+        && characters.all(|character| character.is_alphanumeric() || character == '_')
+}
+
+/// Whether `text` reads as one operator token.
+fn is_operator(text: &str) -> bool {
+    let is_operator_character =
+        |character: char| !character.is_whitespace() && !"()[]{}.:,".contains(character);
+    let mut characters = text.chars();
+    characters.next().is_some_and(|first| {
+        is_operator_character(first) && !first.is_alphanumeric() && !"_\"#".contains(first)
+    }) && characters.all(is_operator_character)
+}
+
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from('"');
+    for character in value.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            character => literal.push(character),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::SexpList;
+
+    fn decompile_lexpr(input: &str) -> String {
+        let sexps = parse_str(input).unwrap();
+        decompile(&sexps[0]).unwrap()
+    }
+
+    #[test]
+    fn prints_idiomatic_lexpr() {
+        for (input, expected) in [
+            ("(x) greater than (y)", "x. greater than: y"),
+            ("hello world", "hello world"),
+            ("f 123", "f 123"),
+            ("f x y", "f x y"),
+            ("(f) x y", "f: x y"),
+            ("(f) (a) x y", "(f) (a) x y"),
+            ("x. f", "x. f"),
+            ("f: (x) (y)", "f (x): y"),
+            ("plus 2 3", "plus 2 3"),
+            ("f: g: x", "f: g: x"),
+            ("x. f. g", "x. f. g"),
+            ("g (f: x)", "g: f: x"),
+            ("n *: (n - 1) !", "n * ((n - 1) !)"),
+            ("- n", "- n"),
+            ("f -1 (-2.5)", "f -1 -2.5"),
+            ("f 0xff 1_000 1.5E3", "f 0xff 1_000 1.5E3"),
+            ("a + b, c", "a + b"),
+            ("a + b * c", "a + b * c"),
+            ("x - - y", "x - - y"),
+            ("(+) (a) (b) (c)", "(+) (a) (b) (c)"),
+            ("f [x, g y] {k: v}", "f [x, g y] {k: v}"),
+            ("[], {k. f: a: b}", "[]"),
+            ("{k. f: a: b}", "{k. f: a: b}"),
+            ("s \"say \\\"hi\\\"\\n\"", "s \"say \\\"hi\\\"\\n\""),
+            (
+                "def (n. fib): if (n = 0) then 0 else: n - 1 . fib",
+                "def (n. fib): if (n = 0) then 0 else: n - 1. fib",
+            ),
+        ] {
+            assert_eq!(decompile_lexpr(input), expected, "decompiling {}", input);
+        }
+    }
+
+    #[test]
+    fn keeps_words_together_without_mixfix() {
+        let sexps = crate::reader::read_str("(if-then-else c a b) (greater-than x y)").unwrap();
+        assert_eq!(decompile(&sexps[0]).unwrap(), "if then else (c) (a): b");
+        assert_eq!(decompile(&sexps[1]).unwrap(), "x. greater than: y");
+    }
+
+    #[test]
+    fn decompiles_read_numbers() {
+        let sexps = crate::reader::read_str("(f 1e-9 +2)").unwrap();
+        assert_eq!(decompile(&sexps[0]).unwrap(), "f 1e-9 2");
+    }
+
+    #[test]
+    fn rejects_unrepresentable_sexps() {
+        let word = parse_str("x").unwrap().remove(0);
+        let span = word.span();
+        assert!(matches!(
+            decompile(&Sexp::List(SexpList::new(vec![word], span))),
+            Err(DecompileError::Unrepresentable { .. })
+        ));
+        assert!(decompile(&Sexp::List(SexpList::new(vec![], span))).is_err());
+    }
+}
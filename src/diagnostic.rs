@@ -0,0 +1,224 @@
+use crate::{
+    parse::ParseError,
+    tokenizer::{Span, TokenKind, TokenizeError},
+};
+
+/// A problem found in Lexpr source, ready to be shown to a person.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    /// Names the Lexpr rule involved and how to satisfy it.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with the offending line of `source` and a caret
+    /// underline, like:
+    ///
+    /// ```text
+    /// error: unexpected `)`
+    ///  --> example.lexpr:1:4
+    ///   |
+    /// 1 | x. )
+    ///   |    ^
+    ///   = hint: dot rule: `x. f` passes `x` as the first argument of `f`, so a dot must be followed by a call
+    /// ```
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let start = self.span.start;
+        let line_number = start.line_number.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let line = source
+            .lines()
+            .nth(start.line_number - 1)
+            .unwrap_or_default();
+        let indentation = line
+            .chars()
+            .take(start.column_number - 1)
+            .map(|character| if character == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let underline_length = if self.span.end.line_number == start.line_number {
+            self.span.end.column_number - start.column_number
+        } else {
+            line.chars().count() + 1 - start.column_number
+        };
+
+        let mut rendered = format!(
+            "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.message,
+            gutter,
+            file_name,
+            start.line_number,
+            start.column_number,
+            gutter,
+            line_number,
+            line,
+            gutter,
+            indentation,
+            "^".repeat(underline_length.max(1)),
+        );
+        if let Some(hint) = &self.hint {
+            rendered.push_str(&format!("{} = hint: {}\n", gutter, hint));
+        }
+        rendered
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic {
+            message: error.to_string(),
+            span: error.span(),
+            hint: hint(error),
+        }
+    }
+}
+
+const DOT_HINT: &str =
+    "dot rule: `x. f` passes `x` as the first argument of `f`, so a dot must be followed by a call";
+const COLON_HINT: &str =
+    "colon rule: `f: x` passes `x` as the last argument of `f`, so a colon needs an expression on both sides";
+const COMMA_HINT: &str =
+    "comma rule: a comma separates two expressions, so it needs an expression on both sides";
+
+fn hint(error: &ParseError) -> Option<String> {
+    let hint = match error {
+        ParseError::TokenizeError(error) => match error {
+            TokenizeError::InvalidEscapeSequence { .. } => {
+                "strings support the escapes `\\n`, `\\t`, `\\\\` and `\\\"`"
+            }
+            TokenizeError::UnterminatedBlockComment { .. } => {
+                "block comments nest, so every `#|` needs its own `|#`"
+            }
+            TokenizeError::UnterminatedStringLiteral { .. } => "close the string with `\"`",
+            TokenizeError::UnexpectedCharacter { .. }
+            | TokenizeError::FailedToParseNumber { .. } => return None,
+        },
+        ParseError::UnexpectedToken {
+            expected: None,
+            previous: Some(previous),
+            ..
+        }
+        | ParseError::UnexpectedEof {
+            expected: None,
+            previous: Some(previous),
+            ..
+        } if separator_hint(previous).is_some() => separator_hint(previous)?,
+        ParseError::UnexpectedToken {
+            expected: Some(expected),
+            ..
+        }
+        | ParseError::UnexpectedEof {
+            expected: Some(expected),
+            ..
+        } => {
+            return Some(format!(
+                "parentheses rule: brackets group an atomic expression and must be closed by {}",
+                expected
+            ))
+        }
+        ParseError::UnexpectedToken {
+            token,
+            previous,
+            layout_closed,
+            ..
+        } => match &token.kind {
+            TokenKind::Dot | TokenKind::Colon | TokenKind::Comma => separator_hint(&token.kind)?,
+            TokenKind::RightParenthesis
+                if previous.as_deref() == Some(&TokenKind::LeftParenthesis) =>
+            {
+                "parentheses rule: parentheses group an expression, so `()` needs one inside"
+            }
+            TokenKind::RightParenthesis | TokenKind::RightBrace | TokenKind::RightBracket => {
+                "this closing bracket has no matching opening bracket"
+            }
+            _ if *layout_closed => {
+                "merging rule: layout closed the previous form before this line, so the two cannot merge, separate them with a comma"
+            }
+            _ => return None,
+        },
+        ParseError::UnexpectedEof { .. } => return None,
+        ParseError::MapEntryWithoutValue { .. } => {
+            "brackets rule: the entries of `{...}` are `key: value` pairs separated by commas"
+        }
+    };
+    Some(hint.to_string())
+}
+
+fn separator_hint(kind: &TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Dot => Some(DOT_HINT),
+        TokenKind::Colon => Some(COLON_HINT),
+        TokenKind::Comma => Some(COMMA_HINT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_str, parse_str_with_options, ParseOptions};
+
+    fn render(source: &str) -> String {
+        Diagnostic::from(&parse_str(source).unwrap_err()).render("example.lexpr", source)
+    }
+
+    fn hint_with_options(source: &str, options: ParseOptions) -> Option<String> {
+        Diagnostic::from(&parse_str_with_options(source, options).unwrap_err()).hint
+    }
+
+    #[test]
+    fn renders_source_line_and_hint() {
+        assert_eq!(
+            render("a,\nx. )"),
+            "\
+error: unexpected `)`
+ --> example.lexpr:2:4
+  |
+2 | x. )
+  |    ^
+  = hint: dot rule: `x. f` passes `x` as the first argument of `f`, so a dot must be followed by a call
+"
+        );
+        assert_eq!(
+            render("f (x"),
+            "\
+error: unexpected end of input, expected `)`
+ --> example.lexpr:1:5
+  |
+1 | f (x
+  |     ^
+  = hint: parentheses rule: brackets group an atomic expression and must be closed by `)`
+"
+        );
+        assert_eq!(
+            render("s \"a\\qb\""),
+            "\
+error: invalid escape sequence `\\q`
+ --> example.lexpr:1:5
+  |
+1 | s \"a\\qb\"
+  |     ^^
+  = hint: strings support the escapes `\\n`, `\\t`, `\\\\` and `\\\"`
+"
+        );
+    }
+
+    #[test]
+    fn hints_only_what_applies() {
+        assert_eq!(
+            hint_with_options("()", ParseOptions::default()).as_deref(),
+            Some("parentheses rule: parentheses group an expression, so `()` needs one inside")
+        );
+        let layout = ParseOptions {
+            layout: true,
+            ..Default::default()
+        };
+        assert!(hint_with_options("a b\nc d", layout)
+            .is_some_and(|hint| hint.starts_with("merging rule: layout closed")));
+        assert_eq!(
+            hint_with_options("f (x) y]", ParseOptions::default()).as_deref(),
+            Some("this closing bracket has no matching opening bracket")
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{
+    cst::{parse_cst, CstNode, SyntaxKind},
+    name::{MixfixName, NamePart},
+    parse::{parse_str_recovering, ParseOptions},
+    sexp::{Sexp, SexpList},
+    tokenizer::{Span, TokenKind},
+};
+
+/// The rule of the readme that produced an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Words not separated by delimiters merge into one name, which calls
+    /// the atomic expressions between them.
+    Merge,
+    Dot,
+    Colon,
+    /// Several expressions in one pair of parentheses.
+    Comma,
+    /// A binary call grouped by [`ParseOptions::operators`].
+    Precedence,
+    /// A parenthesized expression, an argument that does not merge or the
+    /// head of a call without words.
+    Parentheses,
+    /// A vector in `[...]` or a map in `{...}`, see
+    /// [`ParseOptions::collections`].
+    Brackets,
+    /// A name, number or string as written.
+    Atom,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Rule::Merge => "merge rule: words not separated by a dot, colon, comma or bracket are one name, joined by hyphens",
+            Rule::Dot => "dot rule: `x. f` passes `x` as the first argument of `f`",
+            Rule::Colon => "colon rule: `f: x` passes `x` as the last argument of `f`",
+            Rule::Comma => "comma rule: `(a, b)` lists the expressions separated by commas",
+            Rule::Precedence => "precedence rule: the operator table groups `a + b * c` as `a + (b * c)`",
+            Rule::Parentheses => "parentheses rule: an expression in parentheses is atomic and does not merge with the words around it",
+            Rule::Brackets => "brackets rule: `[a, b]` is a vector and `{k: v}` a map of the entries separated by commas",
+            Rule::Atom => "a name, number or string stands for itself",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The innermost expression covering the offset.
+    pub sexp: Sexp,
+    pub span: Span,
+    pub rule: Rule,
+}
+
+/// Explains what the Lexpr at byte `offset` of `source_text` translates to:
+/// the innermost S-expression covering it, where it comes from, and by which
+/// rule. `None` outside of any form or inside a form that failed to parse.
+pub fn explain_at(source_text: &str, offset: usize) -> Option<Explanation> {
+    explain_at_with_options(source_text, offset, ParseOptions::default())
+}
+
+pub fn explain_at_with_options(
+    source_text: &str,
+    offset: usize,
+    options: ParseOptions,
+) -> Option<Explanation> {
+    let (sexps, _) = parse_str_recovering(source_text, options.clone());
+    let covers = |sexp: &&Sexp| {
+        let span = sexp.span();
+        span.start.byte_index <= offset && offset < span.end.byte_index
+    };
+    let mut sexp = sexps.iter().find(covers)?;
+    // The name of a call spans the arguments between its words, so the
+    // smallest expression covering the offset is the innermost.
+    while let Some(expr) = match sexp {
+        Sexp::List(list) | Sexp::Vector(list) => list
+            .exprs()
+            .iter()
+            .filter(covers)
+            .min_by_key(|expr| expr.span().len()),
+        Sexp::Map(map) => map
+            .entries()
+            .iter()
+            .flat_map(|(key, value)| [key, value])
+            .find(covers),
+        _ => None,
+    } {
+        sexp = expr;
+    }
+
+    let (cst, _) = parse_cst(source_text, options.clone());
+    // Not the document, which spans a form that is the whole input.
+    let mut nodes = vec![];
+    for form in cst.root.child_nodes() {
+        collect_nodes(form, &mut nodes);
+    }
+    let rule = match sexp {
+        Sexp::List(list) => match nodes.iter().find(|node| node.span == list.span()) {
+            Some(node) if node.kind == SyntaxKind::Dot => Rule::Dot,
+            Some(node) if node.kind == SyntaxKind::Colon => Rule::Colon,
+            // A tagged collection is headed by a name spanning its bracket.
+            _ if matches!(
+                list.exprs().first(),
+                Some(Sexp::Name(name)) if matches!(name.span().slice(source_text), "[" | "{")
+            ) =>
+            {
+                Rule::Brackets
+            }
+            Some(node) if node.kind == SyntaxKind::Parenthesized => Rule::Comma,
+            _ if is_grouped(list, &options) => Rule::Precedence,
+            // Without words, the first argument heads the call.
+            Some(node) if parenthesized_within(node, list.exprs().first()?.span()) => {
+                Rule::Parentheses
+            }
+            _ => Rule::Merge,
+        },
+        Sexp::Vector(_) | Sexp::Map(_) => Rule::Brackets,
+        Sexp::Error(_) => return None,
+        _ => {
+            let words = cst
+                .root
+                .tokens()
+                .filter(|token| {
+                    matches!(
+                        token.kind,
+                        TokenKind::Identifier(_) | TokenKind::Operator(_)
+                    ) && sexp.span().start <= token.span.start
+                        && token.span.end <= sexp.span().end
+                })
+                .count();
+            if words > 1 {
+                Rule::Merge
+            } else if nodes.iter().any(|node| parenthesizes(node, sexp.span())) {
+                Rule::Parentheses
+            } else {
+                Rule::Atom
+            }
+        }
+    };
+    Some(Explanation {
+        sexp: sexp.clone(),
+        span: sexp.span(),
+        rule,
+    })
+}
+
+fn collect_nodes<'a>(node: &'a CstNode, nodes: &mut Vec<&'a CstNode>) {
+    nodes.push(node);
+    for child in node.child_nodes() {
+        collect_nodes(child, nodes);
+    }
+}
+
+/// Whether `list` is a binary call of an operator in the operator table.
+fn is_grouped(list: &SexpList, options: &ParseOptions) -> bool {
+    let [Sexp::Name(name), _, _] = list.exprs() else {
+        return false;
+    };
+    match name.mixfix().map(MixfixName::to_parts).as_deref() {
+        Some([NamePart::Hole, NamePart::Word(operator), NamePart::Hole]) => {
+            options.operators.contains_key(operator)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `node` is a pair of parentheses around exactly `span`.
+fn parenthesizes(node: &CstNode, span: Span) -> bool {
+    node.kind == SyntaxKind::Parenthesized && node.child_nodes().map(|child| child.span).eq([span])
+}
+
+/// Whether a child of `node` is a pair of parentheses around `span`.
+fn parenthesized_within(node: &CstNode, span: Span) -> bool {
+    node.child_nodes().any(|child| {
+        child.kind == SyntaxKind::Parenthesized
+            && child.span.start < span.start
+            && span.end < child.span.end
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain(source: &str, at: &str) -> (String, String, Rule) {
+        let explanation = explain_at(source, source.find(at).unwrap()).unwrap();
+        (
+            explanation.sexp.stringify(),
+            explanation.span.slice(source).to_string(),
+            explanation.rule,
+        )
+    }
+
+    #[test]
+    fn explains_each_rule() {
+        let source = "x. greater than: y, x. f y, (f) (a b), f: (z), c: (1, \"s\")";
+        for (at, sexp, text, rule) in [
+            ("greater", "greater-than", "greater than", Rule::Merge),
+            ("x.", "x", "x", Rule::Atom),
+            (
+                ". g",
+                "(greater-than x y)",
+                "x. greater than: y",
+                Rule::Colon,
+            ),
+            (". f", "(f-y x)", "x. f y", Rule::Dot),
+            ("f)", "f", "f", Rule::Parentheses),
+            (") (a", "(f (a-b))", "(f) (a b)", Rule::Parentheses),
+            ("a b", "a-b", "a b", Rule::Merge),
+            ("z", "z", "z", Rule::Parentheses),
+            (", \"", "(1 \"s\")", "(1, \"s\")", Rule::Comma),
+            ("1,", "1", "1", Rule::Atom),
+        ] {
+            assert_eq!(
+                explain(source, at),
+                (sexp.to_string(), text.to_string(), rule),
+                "explaining {}",
+                at
+            );
+        }
+    }
+
+    #[test]
+    fn explains_a_form_that_is_the_whole_input() {
+        for (source, at, sexp, rule) in [
+            ("x. f", ". f", "(f x)", Rule::Dot),
+            ("f: x", ": x", "(f x)", Rule::Colon),
+        ] {
+            assert_eq!(
+                explain(source, at),
+                (sexp.to_string(), source.to_string(), rule),
+                "explaining {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn explains_precedence() {
+        let source = "a + b * c";
+        let options = ParseOptions {
+            operators: crate::arithmetic_operators(),
+            ..Default::default()
+        };
+        let explain = |at| {
+            let explanation =
+                explain_at_with_options(source, source.find(at).unwrap(), options.clone()).unwrap();
+            (explanation.sexp.stringify(), explanation.rule)
+        };
+        assert_eq!(
+            explain(" +"),
+            ("(+ a (* b c))".to_string(), Rule::Precedence)
+        );
+        assert_eq!(explain(" *"), ("(* b c)".to_string(), Rule::Precedence));
+        assert_eq!(
+            explain_at(source, 2).unwrap().rule,
+            Rule::Merge,
+            "without a table, the operators merge"
+        );
+    }
+
+    #[test]
+    fn explains_brackets() {
+        let source = "f [a, b c] {k: v}";
+        assert_eq!(
+            explain(source, "[a"),
+            (
+                "[a (b-c)]".to_string(),
+                "[a, b c]".to_string(),
+                Rule::Brackets
+            )
+        );
+        assert_eq!(
+            explain(source, "b c"),
+            ("b-c".to_string(), "b c".to_string(), Rule::Merge)
+        );
+        assert_eq!(
+            explain(source, "v"),
+            ("v".to_string(), "v".to_string(), Rule::Atom)
+        );
+        let options = ParseOptions {
+            collections: crate::Collections::Tagged,
+            ..Default::default()
+        };
+        let explanation =
+            explain_at_with_options(source, source.find(": v").unwrap(), options).unwrap();
+        assert_eq!(explanation.sexp.stringify(), "(map k v)");
+        assert_eq!(explanation.rule, Rule::Brackets);
+    }
+
+    #[test]
+    fn explains_nothing_outside_forms() {
+        assert!(explain_at("f x, ", 4).is_none());
+        assert!(explain_at("f (, g", 0).is_none());
+        let explanation = explain_at("n *: (n - 1) !", 8).unwrap();
+        assert_eq!(explanation.sexp.stringify(), "-");
+        assert_eq!(explanation.rule, Rule::Atom);
+    }
+}
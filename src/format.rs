@@ -0,0 +1,655 @@
+use std::collections::HashSet;
+
+use crate::{
+    cst::{parse_cst, Cst, CstElement, CstNode, CstToken, SyntaxKind, Trivia, TriviaKind},
+    parse::{parse_str_with_options, ParseError, ParseOptions},
+    sexp::Sexp,
+    tokenizer::TokenKind,
+};
+
+/// Indentation added for each nested line of a form.
+const INDENT: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Lines longer than this are broken where the rules allow.
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { width: 80 }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The input does not parse, nothing is formatted.
+    Parse(Vec<ParseError>),
+    /// The formatted text would translate differently from the input, which
+    /// is a bug of the formatter.
+    ChangedTranslation { formatted: String },
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Parse(errors) => write!(f, "input has {} parse error(s)", errors.len()),
+            FormatError::ChangedTranslation { .. } => {
+                write!(f, "formatting would change the translation")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Formats `input_text` canonically: `x. f`, `f: x` and `a, b` spacing,
+/// parentheses that do not change the translation removed, and forms longer
+/// than the width broken like the fib example of the readme:
+///
+/// ```text
+/// def (n. fib):
+///   if (n = 0)
+///   then 0
+///   else: n - 1. fib. +: n - 2. fib
+/// ```
+///
+/// Comments between top-level forms are kept, a form with comments inside is
+/// left as written at the indentation of its line. The result is checked to
+/// translate to the same S-expressions as the input under `parse_options`.
+pub fn format_str(
+    input_text: &str,
+    parse_options: &ParseOptions,
+    options: &FormatOptions,
+) -> Result<String, FormatError> {
+    let (cst, errors) = parse_cst(input_text, parse_options.clone());
+    if !errors.is_empty() {
+        return Err(FormatError::Parse(errors));
+    }
+    let expected =
+        translate(input_text, parse_options).map_err(|error| FormatError::Parse(vec![error]))?;
+
+    let mut formatter = Formatter {
+        parse_options,
+        width: options.width,
+        unwrapped: HashSet::new(),
+        out: String::new(),
+    };
+    formatter.unwrap_grouping_parentheses(&cst.root, false);
+
+    let formatted = formatter.document(&cst);
+    if translate(&formatted, parse_options).is_ok_and(|sexps| sexps == expected) {
+        Ok(formatted)
+    } else {
+        Err(FormatError::ChangedTranslation { formatted })
+    }
+}
+
+fn translate(text: &str, parse_options: &ParseOptions) -> Result<Vec<String>, ParseError> {
+    Ok(parse_str_with_options(text, parse_options.clone())?
+        .iter()
+        .map(Sexp::stringify)
+        .collect())
+}
+
+/// The expression that `node` only groups, when it is parentheses around a
+/// single expression.
+fn grouped(node: &CstNode) -> Option<&CstNode> {
+    let mut children = node.child_nodes();
+    match (children.next(), children.next()) {
+        (Some(inner), None) if is_parenthesized(node) => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_parenthesized(node: &CstNode) -> bool {
+    node.kind == SyntaxKind::Parenthesized
+        && node
+            .tokens()
+            .next()
+            .is_some_and(|open| open.kind == TokenKind::LeftParenthesis)
+}
+
+/// Whether `inner` translates the same without the parentheses around it,
+/// which are the child at `index` of `parent`. `parent_after_colon` tells
+/// whether `parent` is an argument after a colon.
+fn can_unwrap(parent: &CstNode, parent_after_colon: bool, index: usize, inner: &CstNode) -> bool {
+    let is_atom = |accepts: fn(&TokenKind) -> bool| {
+        inner.kind == SyntaxKind::Atom && inner.tokens().all(|token| accepts(&token.kind))
+    };
+    let is_literal = is_atom(|kind| {
+        matches!(
+            kind,
+            TokenKind::StringLiteral(_) | TokenKind::NumberLiteral { .. }
+        )
+    });
+    let is_operand = is_literal || is_atom(|kind| matches!(kind, TokenKind::Identifier(_)));
+    match (parent.kind, index, inner.kind) {
+        (SyntaxKind::Document | SyntaxKind::Parenthesized, _, _) => true,
+        // `(f) x y` is headed by `f` only while `f` is in parentheses, and
+        // after a colon `(f) (x)` passes two arguments but `f (x)` one.
+        (SyntaxKind::Call, 0, _) => {
+            is_parenthesized(inner)
+                || ((is_literal || (is_operand && !parent_after_colon))
+                    && !parent.child_nodes().any(is_word))
+        }
+        // Words would merge with the words of the call.
+        (SyntaxKind::Call, _, kind) => is_literal || kind == SyntaxKind::Parenthesized,
+        (SyntaxKind::OperatorCall, _, SyntaxKind::Call | SyntaxKind::Parenthesized) => true,
+        (SyntaxKind::OperatorCall, _, _) => is_operand,
+        (SyntaxKind::Dot, 0, SyntaxKind::Dot) => true,
+        (
+            SyntaxKind::Dot,
+            _,
+            SyntaxKind::OperatorCall | SyntaxKind::Call | SyntaxKind::Parenthesized,
+        ) => true,
+        (SyntaxKind::Dot, _, _) => is_operand,
+        // Merged words before and after a colon translate differently from
+        // a call, so `(f x): y` and `f: (x y)` keep theirs.
+        (SyntaxKind::Colon, 0, SyntaxKind::Colon) => false,
+        (
+            SyntaxKind::Colon,
+            _,
+            SyntaxKind::Colon
+            | SyntaxKind::Dot
+            | SyntaxKind::OperatorCall
+            | SyntaxKind::Parenthesized,
+        ) => true,
+        (SyntaxKind::Colon, _, _) => is_operand,
+        _ => false,
+    }
+}
+
+struct Formatter<'a> {
+    parse_options: &'a ParseOptions,
+    width: usize,
+    /// Parentheses printed without their brackets.
+    unwrapped: HashSet<usize>,
+    out: String,
+}
+
+impl Formatter<'_> {
+    /// Marks the parentheses that only group in their place, innermost
+    /// first, so that the outer ones see what the inner ones print as.
+    fn unwrap_grouping_parentheses(&mut self, node: &CstNode, after_colon: bool) {
+        let children = node.child_nodes().collect::<Vec<_>>();
+        for (index, child) in children.iter().enumerate() {
+            self.unwrap_grouping_parentheses(child, node.kind == SyntaxKind::Colon && index > 0);
+            // `(x)-1` has to end in the bracket that keeps `-1` an operator.
+            let before_glued = children.get(index + 1).is_some_and(|next| is_glued(next));
+            if let Some(inner) = grouped(child).filter(|_| !before_glued) {
+                if can_unwrap(node, after_colon, index, self.printed(inner)) {
+                    self.unwrapped.insert(child.span.start.character_index);
+                }
+            }
+        }
+    }
+
+    /// The node printed for `node`, inside the parentheses dropped around it.
+    fn printed<'a>(&self, mut node: &'a CstNode) -> &'a CstNode {
+        while let Some(inner) =
+            grouped(node).filter(|_| self.unwrapped.contains(&node.span.start.character_index))
+        {
+            node = inner;
+        }
+        node
+    }
+
+    fn document(&mut self, cst: &Cst) -> String {
+        self.out.clear();
+        let forms = cst.root.child_nodes().collect::<Vec<_>>();
+        // A form copied as written keeps the indentation of its line, which
+        // its other lines are read against under layout and
+        // `newline_as_comma`, so commas separate it from its neighbours.
+        let indentations = forms
+            .iter()
+            .map(|form| {
+                let tokens = form.tokens().collect::<Vec<_>>();
+                let first = tokens.first().filter(|_| has_comments_inside(&tokens))?;
+                cst.root
+                    .tokens()
+                    .find(|token| token.span.start.line_number == first.span.start.line_number)
+                    .map(|token| token.span.start.column_number - 1)
+                    .filter(|&indentation| indentation > 0)
+            })
+            .collect::<Vec<_>>();
+        for (index, form) in forms.iter().enumerate() {
+            let tokens = form.tokens().collect::<Vec<_>>();
+            let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+                continue;
+            };
+            if index > 0 && newlines_before_comments(&first.leading_trivia) > 1 {
+                self.out.push('\n');
+            }
+            for comment in comments(&first.leading_trivia) {
+                self.out.push_str(comment);
+                self.out.push('\n');
+            }
+            if has_comments_inside(&tokens) {
+                self.out
+                    .push_str(&" ".repeat(indentations[index].unwrap_or_default()));
+                self.out.push_str(&verbatim(&tokens));
+            } else {
+                self.node(form, 0);
+            }
+            let separator = separator_after(&cst.root, form);
+            let indented = indentations[index].is_some()
+                || indentations.get(index + 1).is_some_and(Option::is_some);
+            if index + 1 < forms.len() && (indented || !self.parse_options.newline_as_comma) {
+                self.out.push(',');
+            }
+            let trailing_comments =
+                comments(&last.trailing_trivia).chain(separator.into_iter().flat_map(|comma| {
+                    comments(&comma.leading_trivia).chain(comments(&comma.trailing_trivia))
+                }));
+            for comment in trailing_comments {
+                self.out.push(' ');
+                self.out.push_str(comment);
+            }
+            self.out.push('\n');
+        }
+        let end_comments = comments(&cst.end_trivia).collect::<Vec<_>>();
+        if !forms.is_empty()
+            && !end_comments.is_empty()
+            && newlines_before_comments(&cst.end_trivia) > 1
+        {
+            self.out.push('\n');
+        }
+        for comment in end_comments {
+            self.out.push_str(comment);
+            self.out.push('\n');
+        }
+        std::mem::take(&mut self.out)
+    }
+
+    /// Prints `node` from the current column, broken over lines indented by
+    /// at least `indent` when it does not fit.
+    fn node(&mut self, node: &CstNode, indent: usize) {
+        let flat = self.flat(node);
+        if !has_continued_block(node) && self.fits(&flat) {
+            self.out.push_str(&flat);
+            return;
+        }
+        let children = node.child_nodes().collect::<Vec<_>>();
+        match (node.kind, children.as_slice()) {
+            (SyntaxKind::Parenthesized, [inner])
+                if self.unwrapped.contains(&node.span.start.character_index) =>
+            {
+                self.node(inner, indent)
+            }
+            (SyntaxKind::Parenthesized, elements) => {
+                let (open, close) = brackets(node);
+                self.out.push_str(&open);
+                let element_indent = self.column();
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                        self.newline(element_indent);
+                    }
+                    self.node(element, element_indent);
+                }
+                self.out.push_str(&close);
+            }
+            // Layout closed the block of a nested colon, the arguments after
+            // it stay one per line so that it still does.
+            (SyntaxKind::Colon, [left, arguments @ ..]) if arguments.len() > 1 => {
+                self.node(left, indent);
+                self.out.push(':');
+                let block_indent = self.line_indentation() + INDENT;
+                for argument in arguments {
+                    self.newline(block_indent);
+                    self.node(argument, block_indent);
+                }
+            }
+            (SyntaxKind::Colon, [left, right]) => {
+                let groups = groups(left);
+                if groups.len() > 1 {
+                    self.groups(&groups, indent);
+                } else {
+                    self.node(left, indent);
+                }
+                self.out.push(':');
+                let right_flat = self.flat(right);
+                if groups.len() > 1
+                    || has_continued_block(right)
+                    || self.fits(&format!(" {}", right_flat))
+                {
+                    self.out.push(' ');
+                    let right_indent = self.line_indentation() + INDENT;
+                    self.node(right, right_indent);
+                } else {
+                    // Deeper than the line of the colon, whose block layout
+                    // would close otherwise.
+                    let right_indent = (indent + INDENT).max(self.line_indentation() + INDENT);
+                    self.newline(right_indent);
+                    self.node(right, right_indent);
+                }
+            }
+            (SyntaxKind::Dot, [left, right]) => {
+                self.node(left, indent);
+                self.out.push('.');
+                let right_flat = self.flat(right);
+                if self.fits(&format!(" {}", right_flat)) {
+                    self.out.push(' ');
+                    self.out.push_str(&right_flat);
+                } else {
+                    self.newline(indent + INDENT);
+                    self.node(right, indent + INDENT);
+                }
+            }
+            (SyntaxKind::Call | SyntaxKind::OperatorCall, _) => {
+                let groups = groups(node);
+                if groups.len() > 1 {
+                    self.groups(&groups, indent);
+                } else {
+                    for (index, child) in children.iter().enumerate() {
+                        if index > 0 && !is_glued(child) {
+                            self.out.push(' ');
+                        }
+                        self.node(child, indent + INDENT);
+                    }
+                }
+            }
+            _ => self.out.push_str(&flat),
+        }
+    }
+
+    /// Prints each group of a call on its own line, like `then 0` and
+    /// `else` in the fib example. Lines that continue a top-level form are
+    /// indented so that layout does not close the form.
+    fn groups(&mut self, groups: &[Vec<&CstNode>], indent: usize) {
+        let indent = indent.max(INDENT);
+        for (index, group) in groups.iter().enumerate() {
+            if index > 0 {
+                self.newline(indent);
+            }
+            for (index, child) in group.iter().enumerate() {
+                if index > 0 && !is_glued(child) {
+                    self.out.push(' ');
+                }
+                self.node(child, indent + INDENT);
+            }
+        }
+    }
+
+    /// `node` on one line.
+    fn flat(&self, node: &CstNode) -> String {
+        let children = node.child_nodes().collect::<Vec<_>>();
+        match (node.kind, children.as_slice()) {
+            (SyntaxKind::Parenthesized, [inner])
+                if self.unwrapped.contains(&node.span.start.character_index) =>
+            {
+                self.flat(inner)
+            }
+            (SyntaxKind::Parenthesized, elements) => {
+                let (open, close) = brackets(node);
+                format!(
+                    "{}{}{}",
+                    open,
+                    elements
+                        .iter()
+                        .map(|element| self.flat(element))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    close
+                )
+            }
+            (SyntaxKind::Colon, [left, right]) => {
+                format!("{}: {}", self.flat(left), self.flat(right))
+            }
+            (SyntaxKind::Dot, [left, right]) => {
+                format!("{}. {}", self.flat(left), self.flat(right))
+            }
+            (SyntaxKind::Call | SyntaxKind::OperatorCall, children) => {
+                let mut flat = String::new();
+                for (index, child) in children.iter().enumerate() {
+                    if index > 0 && !is_glued(child) {
+                        flat.push(' ');
+                    }
+                    flat.push_str(&self.flat(child));
+                }
+                flat
+            }
+            _ => node.tokens().map(|token| token.text.as_str()).collect(),
+        }
+    }
+
+    fn fits(&self, text: &str) -> bool {
+        self.column() + text.chars().count() <= self.width
+    }
+
+    fn column(&self) -> usize {
+        self.out
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+    }
+
+    fn line_indentation(&self) -> usize {
+        let line = self.out.rsplit('\n').next().unwrap_or_default();
+        line.len() - line.trim_start_matches(' ').len()
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+    }
+}
+
+/// Whether a colon in `node` goes on after a block that layout closed, see
+/// [`ParseOptions::layout`], which cannot be printed on one line.
+fn has_continued_block(node: &CstNode) -> bool {
+    (node.kind == SyntaxKind::Colon && node.child_nodes().count() > 2)
+        || node.child_nodes().any(has_continued_block)
+}
+
+/// The children of a call split where a word follows an argument, so
+/// `if (n = 0) then 0 else` is `if (n = 0)`, `then 0` and `else`.
+fn groups(node: &CstNode) -> Vec<Vec<&CstNode>> {
+    if !matches!(node.kind, SyntaxKind::Call | SyntaxKind::OperatorCall) {
+        return vec![vec![node]];
+    }
+    let mut groups: Vec<Vec<&CstNode>> = vec![];
+    let mut previous_is_word = false;
+    for child in node.child_nodes() {
+        let is_word = is_word(child);
+        match groups.last_mut() {
+            Some(group) if !is_word || previous_is_word || is_glued(child) => group.push(child),
+            _ => groups.push(vec![child]),
+        }
+        previous_is_word = is_word;
+    }
+    groups
+}
+
+fn is_word(node: &CstNode) -> bool {
+    node.kind == SyntaxKind::Atom
+        && node.tokens().all(|token| {
+            matches!(
+                token.kind,
+                TokenKind::Identifier(_) | TokenKind::Operator(_)
+            )
+        })
+}
+
+/// Whether `node` is an operator like the `-1` of `n-1`, which reads as
+/// the number -1 unless it stays right after the expression before it.
+fn is_glued(node: &CstNode) -> bool {
+    node.kind == SyntaxKind::Atom
+        && node.tokens().all(|token| {
+            matches!(&token.kind, TokenKind::Operator(operator)
+                if operator.strip_prefix('-').is_some_and(|rest| rest.starts_with(|character: char| character.is_ascii_digit())))
+        })
+}
+
+fn brackets(node: &CstNode) -> (String, String) {
+    let bracket =
+        |token: Option<&CstToken>| token.map(|token| token.text.clone()).unwrap_or_default();
+    (bracket(node.tokens().next()), bracket(node.tokens().last()))
+}
+
+/// The comma right after `form` in the document, if any.
+fn separator_after<'a>(root: &'a CstNode, form: &CstNode) -> Option<&'a CstToken> {
+    let mut children = root
+        .children
+        .iter()
+        .skip_while(|child| !matches!(child, CstElement::Node(node) if std::ptr::eq(node, form)))
+        .skip(1);
+    match children.next() {
+        Some(CstElement::Token(token)) if token.kind == TokenKind::Comma => Some(token),
+        _ => None,
+    }
+}
+
+fn comments(trivia: &[Trivia]) -> impl Iterator<Item = &str> {
+    trivia
+        .iter()
+        .filter(|trivia| {
+            matches!(
+                trivia.kind,
+                TriviaKind::LineComment | TriviaKind::BlockComment
+            )
+        })
+        .map(|trivia| trivia.text.as_str())
+}
+
+/// More than one means a blank line, as the first newline ends the line of
+/// the previous token.
+fn newlines_before_comments(trivia: &[Trivia]) -> usize {
+    trivia
+        .iter()
+        .take_while(|trivia| trivia.kind == TriviaKind::Whitespace)
+        .map(|trivia| trivia.text.matches('\n').count())
+        .sum()
+}
+
+/// Whether a comment sits between the first and the last token of a form.
+fn has_comments_inside(tokens: &[&CstToken]) -> bool {
+    tokens.iter().enumerate().any(|(index, token)| {
+        (index > 0 && comments(&token.leading_trivia).next().is_some())
+            || (index + 1 < tokens.len() && comments(&token.trailing_trivia).next().is_some())
+    })
+}
+
+/// The source of a form, without the trivia around it.
+fn verbatim(tokens: &[&CstToken]) -> String {
+    let mut text = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 {
+            token
+                .leading_trivia
+                .iter()
+                .for_each(|trivia| text.push_str(&trivia.text));
+        }
+        text.push_str(&token.text);
+        if index + 1 < tokens.len() {
+            token
+                .trailing_trivia
+                .iter()
+                .for_each(|trivia| text.push_str(&trivia.text));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(input: &str, width: usize) -> String {
+        let layout = ParseOptions {
+            layout: true,
+            newline_as_comma: true,
+            ..Default::default()
+        };
+        format_str(input, &layout, &FormatOptions { width }).unwrap()
+    }
+
+    #[test]
+    fn normalizes_spacing_and_parentheses() {
+        assert_eq!(format("x .greater than :y", 80), "x. greater than: y\n");
+        assert_eq!(
+            format("(x),((f)) (y) ,  (n - 1) !", 80),
+            "x\nf (y)\n(n - 1) !\n"
+        );
+        assert_eq!(format("f [a,b]   {c :d}", 80), "f [a, b] {c: d}\n");
+        assert_eq!(
+            format(
+                "((f)) x y, f ((x y)), f: ((x) (y)), (x. f). (g), a - (-1)",
+                80
+            ),
+            "(f) x y\nf (x y)\nf: (x (y))\nx. f. g\na - -1\n"
+        );
+        assert_eq!(
+            format("(f: x): y, x. (f. g), (x) greater than: (y)", 80),
+            "(f: x): y\nx. (f. g)\n(x) greater than: y\n"
+        );
+    }
+
+    #[test]
+    fn breaks_long_forms_like_the_readme() {
+        let fib = "def (n. fib): if (n = 0) then 0 else: if (n = 1) then 1 else: n - 1. fib. +: n - 2. fib";
+        assert_eq!(
+            format(fib, 40),
+            "\
+def (n. fib):
+  if (n = 0)
+  then 0
+  else: if (n = 1)
+    then 1
+    else: n - 1. fib. +: n - 2. fib
+"
+        );
+        assert_eq!(format(&format(fib, 40), 40), format(fib, 40));
+        assert_eq!(format(fib, 200), format!("{}\n", fib));
+
+        let nested = "f:\n  g:\n      x\n  h: y\n  z";
+        assert_eq!(format(nested, 80), "f:\n  g: x\n  h: y\n  z\n");
+        assert_eq!(format("(x. f). (x. f): c", 10), "x. f.\n  (x. f):\n    c\n");
+    }
+
+    #[test]
+    fn keeps_minus_operators_glued() {
+        assert_eq!(format("n-1", 80), "n-1\n");
+        assert_eq!(format("f (x)-1", 80), "f (x)-1\n");
+        assert_eq!(format("0xff-1", 80), "0xff-1\n");
+        assert_eq!(
+            format("((x))-1, f (x) then (y)-1", 10),
+            "(x)-1\nf (x)\n  then (y)-1\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        assert_eq!(
+            format(
+                "# about a\na   b # a\n\n\nc:  (d # inside\n  )\n# end\n",
+                80
+            ),
+            "# about a\na b # a\n\nc:  (d # inside\n  )\n# end\n"
+        );
+        let newline_as_comma = ParseOptions {
+            newline_as_comma: true,
+            ..Default::default()
+        };
+        for (input, expected) in [
+            ("\n x#c\né. 1e3", " x#c\né. 1e3\n"),
+            ("\n x#c\né. 1e3\n y", " x#c\né. 1e3,\ny\n"),
+            ("a, b #c\n 1\nd", "a\nb #c\n 1\nd\n"),
+        ] {
+            assert_eq!(
+                format_str(input, &newline_as_comma, &FormatOptions::default()).unwrap(),
+                expected,
+                "formatting {:?}",
+                input
+            );
+        }
+        assert!(matches!(
+            format_str("f (", &ParseOptions::default(), &FormatOptions::default()),
+            Err(FormatError::Parse(_))
+        ));
+    }
+}
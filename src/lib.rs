@@ -0,0 +1,42 @@
+//! Lexpr is a data format that reads like natural language and translates
+//! to S-expressions, see the readme for the rules.
+//!
+//! ```
+//! let sexps = lexpr::parse_str("x. greater than: y").unwrap();
+//! assert_eq!(sexps[0].stringify(), "(greater-than x y)");
+//! ```
+
+pub mod cst;
+mod decompile;
+mod diagnostic;
+mod explain;
+mod format;
+mod line_index;
+mod name;
+mod number;
+mod parse;
+mod precedence;
+mod pretty;
+mod printer;
+mod reader;
+mod sexp;
+pub mod tokenizer;
+
+pub use decompile::{decompile, DecompileError};
+pub use diagnostic::Diagnostic;
+pub use explain::{explain_at, explain_at_with_options, Explanation, Rule};
+pub use format::{format_str, FormatError, FormatOptions};
+pub use line_index::{LineColumn, LineIndex};
+pub use name::{MixfixName, NameMangler, NamePart};
+pub use num_bigint::BigInt;
+pub use number::Number;
+pub use parse::{
+    parse_str, parse_str_recovering, parse_str_with_options, parse_str_with_trivia, Collections,
+    ParseError, ParseOptions, UnaryOperators,
+};
+pub use precedence::{arithmetic_operators, Associativity, Fixity};
+pub use pretty::{pretty_print, IndentRule, PrettyOptions};
+pub use printer::{Clojure, CommonLisp, Dialect, EmacsLisp, PrintError, Printer, Scheme};
+pub use reader::read_str;
+pub use sexp::{LiteralName, LiteralNumber, LiteralString, Sexp, SexpList, SexpMap};
+pub use tokenizer::{Position, Span, TokenizeError};
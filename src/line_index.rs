@@ -0,0 +1,215 @@
+//! Conversions between the ways editors and Rust count positions in a text:
+//! byte offsets for slicing, character offsets as in [`Position`], and lines
+//! with UTF-8 or UTF-16 columns, the latter being what LSP clients send.
+
+use crate::tokenizer::Position;
+
+/// A zero-based line and column, the unit of the column depends on the
+/// method that made it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The start of every line of a text, built once and queried for any
+/// number of positions.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    text: String,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    /// Character offset of the start of each line.
+    line_character_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut line_character_starts = vec![0];
+        for (character_offset, (byte_offset, character)) in text.char_indices().enumerate() {
+            if character == '\n' {
+                line_starts.push(byte_offset + 1);
+                line_character_starts.push(character_offset + 1);
+            }
+        }
+        Self {
+            text: text.to_string(),
+            line_starts,
+            line_character_starts,
+        }
+    }
+
+    /// The character offset of `byte_offset`, offsets past the end or inside
+    /// a character round down to a character boundary.
+    pub fn character_offset(&self, byte_offset: usize) -> usize {
+        let line = self.line_of(byte_offset);
+        self.line_character_starts[line] + self.columns(line, byte_offset).0
+    }
+
+    /// The byte offset of `character_offset`, the length of the text for
+    /// offsets past the end.
+    pub fn byte_offset(&self, character_offset: usize) -> usize {
+        let line = self
+            .line_character_starts
+            .partition_point(|&start| start <= character_offset)
+            - 1;
+        let skipped = character_offset - self.line_character_starts[line];
+        self.line_text(line)
+            .char_indices()
+            .nth(skipped)
+            .map_or(self.line_end(line), |(byte_offset, _)| {
+                self.line_starts[line] + byte_offset
+            })
+    }
+
+    /// The line of `byte_offset` and its column in UTF-8 bytes.
+    pub fn line_column(&self, byte_offset: usize) -> LineColumn {
+        let line = self.line_of(byte_offset);
+        LineColumn {
+            line,
+            column: self.columns(line, byte_offset).1,
+        }
+    }
+
+    /// The line of `byte_offset` and its column in UTF-16 code units.
+    pub fn line_column_utf16(&self, byte_offset: usize) -> LineColumn {
+        let line = self.line_of(byte_offset);
+        LineColumn {
+            line,
+            column: self.columns(line, byte_offset).2,
+        }
+    }
+
+    /// The byte offset of a UTF-8 column, `None` past the end of the line or
+    /// inside a character.
+    pub fn offset(&self, line_column: LineColumn) -> Option<usize> {
+        let line_text = self.line_text_checked(line_column.line)?;
+        (line_column.column <= line_text.len() && line_text.is_char_boundary(line_column.column))
+            .then(|| self.line_starts[line_column.line] + line_column.column)
+    }
+
+    /// The byte offset of a UTF-16 column, `None` past the end of the line or
+    /// between the two halves of a surrogate pair.
+    pub fn offset_utf16(&self, line_column: LineColumn) -> Option<usize> {
+        let line_text = self.line_text_checked(line_column.line)?;
+        let mut column = 0;
+        for (byte_offset, character) in line_text
+            .char_indices()
+            .chain(Some((line_text.len(), '\n')))
+        {
+            if column == line_column.column {
+                return Some(self.line_starts[line_column.line] + byte_offset);
+            }
+            if column > line_column.column {
+                break;
+            }
+            column += character.len_utf16();
+        }
+        None
+    }
+
+    /// The full [`Position`] of `byte_offset`, as the tokenizer counts it.
+    pub fn position(&self, byte_offset: usize) -> Position {
+        let line = self.line_of(byte_offset);
+        let (characters, bytes, _) = self.columns(line, byte_offset);
+        Position {
+            line_number: line + 1,
+            column_number: characters + 1,
+            character_index: self.line_character_starts[line] + characters,
+            byte_index: self.line_starts[line] + bytes,
+        }
+    }
+
+    fn line_of(&self, byte_offset: usize) -> usize {
+        self.line_starts
+            .partition_point(|&start| start <= byte_offset)
+            - 1
+    }
+
+    /// The text of `line` without its newline.
+    fn line_text(&self, line: usize) -> &str {
+        &self.text[self.line_starts[line]..self.line_end(line)]
+    }
+
+    fn line_text_checked(&self, line: usize) -> Option<&str> {
+        (line < self.line_starts.len()).then(|| self.line_text(line))
+    }
+
+    fn line_end(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next_start| next_start - 1)
+    }
+
+    /// The column of `byte_offset` on `line` in characters, UTF-8 bytes and
+    /// UTF-16 code units, counting only whole characters before it.
+    fn columns(&self, line: usize, byte_offset: usize) -> (usize, usize, usize) {
+        let column = byte_offset - self.line_starts[line];
+        self.line_text(line)
+            .char_indices()
+            .take_while(|&(start, character)| start + character.len_utf8() <= column)
+            .fold((0, 0, 0), |(characters, bytes, units), (_, character)| {
+                (
+                    characters + 1,
+                    bytes + character.len_utf8(),
+                    units + character.len_utf16(),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{Token, Tokenizer};
+
+    #[test]
+    fn converts_offsets_and_columns() {
+        let text = "é = \"𝄞\"\nab𝄞c";
+        let index = LineIndex::new(text);
+        let c = text.rfind('c').unwrap();
+        assert_eq!(c, 18);
+        assert_eq!(index.character_offset(c), 11);
+        assert_eq!(index.byte_offset(11), c);
+        assert_eq!(index.line_column(c), LineColumn { line: 1, column: 6 });
+        assert_eq!(
+            index.line_column_utf16(c),
+            LineColumn { line: 1, column: 4 }
+        );
+        assert_eq!(index.offset(LineColumn { line: 1, column: 6 }), Some(c));
+        assert_eq!(
+            index.offset_utf16(LineColumn { line: 1, column: 4 }),
+            Some(c)
+        );
+        // Inside the musical symbol, which takes two UTF-16 code units.
+        assert_eq!(index.offset_utf16(LineColumn { line: 1, column: 3 }), None);
+        assert_eq!(index.offset(LineColumn { line: 1, column: 4 }), None);
+        assert_eq!(index.offset(LineColumn { line: 2, column: 0 }), None);
+        // The end of each line and of the text.
+        assert_eq!(
+            index.offset_utf16(LineColumn { line: 0, column: 8 }),
+            Some(11)
+        );
+        assert_eq!(
+            index.offset_utf16(LineColumn { line: 1, column: 5 }),
+            Some(19)
+        );
+        assert_eq!(index.byte_offset(100), text.len());
+    }
+
+    #[test]
+    fn matches_tokenizer_positions() {
+        let text = "naïve #| ü |#\n  \"𝄞\" x";
+        let index = LineIndex::new(text);
+        let mut tokenizer = Tokenizer::new(text);
+        while let Some(Token { span, .. }) = tokenizer.next_token().unwrap() {
+            assert_eq!(index.position(span.start.byte_index), span.start);
+            assert_eq!(index.position(span.end.byte_index), span.end);
+            assert_eq!(
+                index.byte_offset(span.start.character_index),
+                span.start.byte_index
+            );
+        }
+    }
+}
@@ -1,7 +1,397 @@
-mod lexpr;
-mod parse;
-pub mod tokenizer;
+use std::io::Read as _;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Hello, world!");
+use lexpr::{
+    Collections, Diagnostic, Dialect, FormatError, FormatOptions, NameMangler, ParseOptions,
+    PrettyOptions, UnaryOperators,
+};
+
+const USAGE: &str = "\
+Usage: lexpr <command> [options] [files...]
+
+Commands:
+  translate  Print the S-expressions each file translates to
+  check      Report parse errors without printing anything else
+  fmt        Format files in place, or stdin to stdout
+  explain    Show the expression at `--at` and the rule that made it
+
+Options:
+  --layout            Make indentation close forms (off-side rule)
+  --newline-as-comma  Separate forms starting at the same indentation
+  --names <style>     Join name words as `hyphen` (default), `mixfix`,
+                      `camel` or `snake`
+  --unary <style>     Translate `- x`, `n !` and `-1` as `merged` names
+                      (default) or as `neg` and bare operator calls
+  --precedence        Group `a + b * c` as `(+ a (* b c))` by the usual
+                      arithmetic, comparison and boolean precedences
+  --collections <style>
+                      Translate `[a, b]` and `{k: v}` as `literals`
+                      (default) or as `tagged` vector and map calls
+  --width <columns>   Line width for `fmt` and `translate` (default 80)
+  --dialect <name>    Print `translate` output for `scheme` (default),
+                      `clojure`, `common-lisp` or `emacs-lisp`
+  --at <line:column>  Position for `explain`, counted from 1:1
+  -h, --help          Print this message
+
+Reads stdin when no file (or `-`) is given.";
+
+/// Exit code for input that failed to parse.
+const EXIT_PARSE_ERROR: u8 = 1;
+/// Exit code for invalid arguments or unreadable files.
+const EXIT_USAGE_ERROR: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Translate,
+    Check,
+    Format,
+    Explain,
+    Help,
+}
+
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    options: ParseOptions,
+    format_options: FormatOptions,
+    dialect: Dialect,
+    /// Line and column for `explain`.
+    at: Option<(usize, usize)>,
+    files: Vec<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        Some("translate") => Command::Translate,
+        Some("check") => Command::Check,
+        Some("fmt") => Command::Format,
+        Some("explain") => Command::Explain,
+        Some("-h" | "--help") => Command::Help,
+        Some(command) => return Err(format!("unknown command `{}`", command)),
+        None => return Err("missing command".to_string()),
+    };
+    let mut options = ParseOptions::default();
+    let mut format_options = FormatOptions::default();
+    let mut dialect = Dialect::default();
+    let mut at = None;
+    let mut files = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--layout" => options.layout = true,
+            "--newline-as-comma" => options.newline_as_comma = true,
+            "--precedence" => options.operators = lexpr::arithmetic_operators(),
+            "--names" => {
+                options.name_mangler = match args.next().as_deref() {
+                    Some("hyphen") => NameMangler::Hyphen,
+                    Some("mixfix") => NameMangler::Mixfix,
+                    Some("camel") => NameMangler::CamelCase,
+                    Some("snake") => NameMangler::SnakeCase,
+                    Some(style) => return Err(format!("unknown name style `{}`", style)),
+                    None => return Err("missing name style after `--names`".to_string()),
+                }
+            }
+            "--unary" => {
+                options.unary_operators = match args.next().as_deref() {
+                    Some("merged") => UnaryOperators::Merged,
+                    Some("neg") => UnaryOperators::Neg,
+                    Some(style) => return Err(format!("unknown unary style `{}`", style)),
+                    None => return Err("missing unary style after `--unary`".to_string()),
+                }
+            }
+            "--collections" => {
+                options.collections = match args.next().as_deref() {
+                    Some("literals") => Collections::Literals,
+                    Some("tagged") => Collections::Tagged,
+                    Some(style) => return Err(format!("unknown collection style `{}`", style)),
+                    None => {
+                        return Err("missing collection style after `--collections`".to_string())
+                    }
+                }
+            }
+            "--width" => {
+                format_options.width = match args.next() {
+                    Some(width) => width
+                        .parse()
+                        .map_err(|_| format!("invalid width `{}`", width))?,
+                    None => return Err("missing columns after `--width`".to_string()),
+                }
+            }
+            "--dialect" => {
+                dialect = match args.next().as_deref() {
+                    Some("scheme") => Dialect::Scheme,
+                    Some("clojure") => Dialect::Clojure,
+                    Some("common-lisp") => Dialect::CommonLisp,
+                    Some("emacs-lisp") => Dialect::EmacsLisp,
+                    Some(name) => return Err(format!("unknown dialect `{}`", name)),
+                    None => return Err("missing dialect after `--dialect`".to_string()),
+                }
+            }
+            "--at" => {
+                let Some(position) = args.next() else {
+                    return Err("missing position after `--at`".to_string());
+                };
+                at = match position
+                    .split_once(':')
+                    .map(|(line, column)| (line.parse::<usize>(), column.parse::<usize>()))
+                {
+                    Some((Ok(line), Ok(column))) if line > 0 && column > 0 => Some((line, column)),
+                    _ => return Err(format!("invalid position `{}`", position)),
+                }
+            }
+            "-h" | "--help" => {
+                return Ok(Args {
+                    command: Command::Help,
+                    options,
+                    format_options,
+                    dialect,
+                    at,
+                    files,
+                })
+            }
+            "-" => files.push(arg),
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option `{}`", option))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if command == Command::Explain && at.is_none() {
+        return Err("`explain` needs a position, like `--at 1:4`".to_string());
+    }
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+    Ok(Args {
+        command,
+        options,
+        format_options,
+        dialect,
+        at,
+        files,
+    })
+}
+
+fn read_input(file: &str) -> std::io::Result<String> {
+    if file == "-" {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(file)
+    }
+}
+
+fn display_name(file: &str) -> &str {
+    if file == "-" {
+        "<stdin>"
+    } else {
+        file
+    }
+}
+
+fn run(args: Args) -> ExitCode {
+    let mut exit_code = 0;
+    for file in &args.files {
+        let input = match read_input(file) {
+            Ok(input) => input,
+            Err(error) => {
+                eprintln!("error: {}: {}", display_name(file), error);
+                exit_code = exit_code.max(EXIT_USAGE_ERROR);
+                continue;
+            }
+        };
+        if args.command == Command::Format {
+            exit_code = exit_code.max(format(file, &input, &args));
+            continue;
+        }
+        if args.command == Command::Explain {
+            exit_code = exit_code.max(explain(file, &input, &args));
+            continue;
+        }
+        let (sexps, errors) = lexpr::parse_str_recovering(&input, args.options.clone());
+        for error in &errors {
+            eprint!(
+                "{}",
+                Diagnostic::from(error).render(display_name(file), &input)
+            );
+        }
+        if !errors.is_empty() {
+            exit_code = exit_code.max(EXIT_PARSE_ERROR);
+        } else if args.command == Command::Translate {
+            let pretty_options = PrettyOptions {
+                width: args.format_options.width,
+                ..PrettyOptions::default()
+            };
+            for sexp in sexps {
+                match lexpr::pretty_print(&sexp, args.dialect.printer(), &pretty_options) {
+                    Ok(printed) => println!("{}", printed),
+                    Err(error) => {
+                        eprintln!("error: {}: {}", display_name(file), error);
+                        exit_code = exit_code.max(EXIT_PARSE_ERROR);
+                    }
+                }
+            }
+        }
+    }
+    ExitCode::from(exit_code)
+}
+
+/// Formats `input` read from `file`, returning the exit code.
+fn format(file: &str, input: &str, args: &Args) -> u8 {
+    match lexpr::format_str(input, &args.options, &args.format_options) {
+        Ok(formatted) if file == "-" => {
+            print!("{}", formatted);
+            0
+        }
+        Ok(formatted) if formatted == input => 0,
+        Ok(formatted) => match std::fs::write(file, formatted) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}: {}", file, error);
+                EXIT_USAGE_ERROR
+            }
+        },
+        Err(FormatError::Parse(errors)) => {
+            for error in &errors {
+                eprint!(
+                    "{}",
+                    Diagnostic::from(error).render(display_name(file), input)
+                );
+            }
+            EXIT_PARSE_ERROR
+        }
+        Err(error) => {
+            eprintln!("error: {}: {}", display_name(file), error);
+            EXIT_PARSE_ERROR
+        }
+    }
+}
+
+/// Prints the expression at `args.at` in `input` and the rule that made it,
+/// returning the exit code.
+fn explain(file: &str, input: &str, args: &Args) -> u8 {
+    let (line, column) = args.at.unwrap_or((1, 1));
+    let explanation = byte_offset(input, line, column)
+        .and_then(|offset| lexpr::explain_at_with_options(input, offset, args.options.clone()));
+    let Some(explanation) = explanation else {
+        eprintln!(
+            "error: {}:{}:{}: no expression here",
+            display_name(file),
+            line,
+            column
+        );
+        return EXIT_USAGE_ERROR;
+    };
+    let (start, end) = (explanation.span.start, explanation.span.end);
+    let printed = args
+        .dialect
+        .printer()
+        .print(&explanation.sexp)
+        .unwrap_or_else(|_| explanation.sexp.stringify());
+    println!(
+        "{}:{}:{}-{}:{}: {}\n  {}",
+        display_name(file),
+        start.line_number,
+        start.column_number,
+        end.line_number,
+        end.column_number,
+        printed,
+        explanation.rule
+    );
+    0
+}
+
+/// The byte offset of a line and a column in characters, both counted from 1.
+fn byte_offset(input: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start = input
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    let line_text = input[line_start..].lines().next().unwrap_or_default();
+    line_text
+        .char_indices()
+        .map(|(byte_offset, _)| byte_offset)
+        .chain(Some(line_text.len()))
+        .nth(column - 1)
+        .map(|byte_offset| line_start + byte_offset)
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Args {
+            command: Command::Help,
+            ..
+        }) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(args) => run(args),
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            ExitCode::from(EXIT_USAGE_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let parsed = args(&["translate", "--layout", "a.lexpr", "b.lexpr"]).unwrap();
+        assert_eq!(parsed.command, Command::Translate);
+        assert!(parsed.options.layout);
+        assert!(!parsed.options.newline_as_comma);
+        assert_eq!(parsed.files, ["a.lexpr", "b.lexpr"]);
+
+        let parsed = args(&["translate", "--names", "snake"]).unwrap();
+        assert_eq!(parsed.options.name_mangler, NameMangler::SnakeCase);
+        assert_eq!(parsed.dialect, Dialect::Scheme);
+
+        let parsed = args(&["translate", "--dialect", "emacs-lisp"]).unwrap();
+        assert_eq!(parsed.dialect, Dialect::EmacsLisp);
+        assert!(args(&["translate", "--dialect", "fortran"]).is_err());
+        assert!(args(&["translate", "--names"]).is_err());
+
+        let parsed = args(&["translate", "--unary", "neg"]).unwrap();
+        assert_eq!(parsed.options.unary_operators, UnaryOperators::Neg);
+        assert!(args(&["translate", "--unary", "prefix"]).is_err());
+        let parsed = args(&["translate", "--collections", "tagged"]).unwrap();
+        assert_eq!(parsed.options.collections, Collections::Tagged);
+        assert!(args(&["translate", "--collections"]).is_err());
+        assert!(
+            args(&["translate", "--precedence"])
+                .unwrap()
+                .options
+                .operators["*"]
+                .precedence
+                > 0
+        );
+
+        let parsed = args(&["fmt", "--width", "60"]).unwrap();
+        assert_eq!(parsed.command, Command::Format);
+        assert_eq!(parsed.format_options.width, 60);
+        assert!(args(&["fmt", "--width", "wide"]).is_err());
+
+        let parsed = args(&["explain", "--at", "2:14"]).unwrap();
+        assert_eq!(parsed.command, Command::Explain);
+        assert_eq!(parsed.at, Some((2, 14)));
+        assert!(args(&["explain"]).is_err());
+        assert!(args(&["explain", "--at", "0:1"]).is_err());
+
+        let parsed = args(&["check"]).unwrap();
+        assert_eq!(parsed.command, Command::Check);
+        assert_eq!(parsed.files, ["-"]);
+
+        assert!(args(&[]).is_err());
+        assert!(args(&["frobnicate"]).is_err());
+        assert!(args(&["check", "--frobnicate"]).is_err());
+    }
 }
@@ -0,0 +1,257 @@
+use itertools::Itertools as _;
+
+/// A piece of a merged name, either a word or the place an argument sat.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NamePart {
+    Word(String),
+    Hole,
+}
+
+/// How the words of a merged name are joined into one symbol, so that
+/// `(x) greater than (y)` reads naturally in the target Lisp.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NameMangler {
+    /// `greater-than`, the naming described in the readme.
+    #[default]
+    Hyphen,
+    /// `_greater-than_`, every argument leaves an underscore where it sat.
+    Mixfix,
+    /// `greaterThan`
+    CamelCase,
+    /// `greater_than`
+    SnakeCase,
+}
+
+impl NameMangler {
+    pub fn mangle(&self, parts: &[NamePart]) -> String {
+        let words = || {
+            parts.iter().filter_map(|part| match part {
+                NamePart::Word(word) => Some(word.as_str()),
+                NamePart::Hole => None,
+            })
+        };
+        match self {
+            // A dot never occurs in an operator, where a hyphen would join
+            // `x - - y` into `---`, a single operator.
+            NameMangler::Hyphen | NameMangler::SnakeCase | NameMangler::CamelCase
+                if words().any(is_operator) =>
+            {
+                words().join(".")
+            }
+            NameMangler::Hyphen => words().join("-"),
+            NameMangler::SnakeCase => words().join("_"),
+            NameMangler::CamelCase => words()
+                .enumerate()
+                .map(|(index, word)| {
+                    let mut characters = word.chars();
+                    match characters.next() {
+                        Some(first) if index > 0 => {
+                            first.to_uppercase().chain(characters).collect()
+                        }
+                        _ => word.to_string(),
+                    }
+                })
+                .collect(),
+            NameMangler::Mixfix => {
+                let mut name = String::new();
+                let mut previous_was_word = false;
+                for part in parts {
+                    match part {
+                        NamePart::Word(word) => {
+                            if previous_was_word {
+                                name.push(if is_operator(word) { '.' } else { '-' });
+                            }
+                            name.push_str(word);
+                            previous_was_word = true;
+                        }
+                        NamePart::Hole => {
+                            name.push('_');
+                            previous_was_word = false;
+                        }
+                    }
+                }
+                name
+            }
+        }
+    }
+}
+
+/// Whether `word` is an operator, all of whose words are operators.
+fn is_operator(word: &str) -> bool {
+    word.chars()
+        .next()
+        .is_some_and(|first| !first.is_alphanumeric() && first != '_')
+}
+
+/// The words of a call together with the places its arguments sat, so that a
+/// definition like `def ((x) greater than (y))` can be matched against the
+/// call `x. greater than: y` no matter how the name was mangled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MixfixName {
+    pub parts: Vec<String>,
+    /// For every argument, the number of words before it, in ascending order.
+    pub holes: Vec<usize>,
+}
+
+impl MixfixName {
+    pub fn from_parts(parts: &[NamePart]) -> MixfixName {
+        let mut name = MixfixName {
+            parts: vec![],
+            holes: vec![],
+        };
+        for part in parts {
+            match part {
+                NamePart::Word(word) => name.parts.push(word.clone()),
+                NamePart::Hole => name.holes.push(name.parts.len()),
+            }
+        }
+        name
+    }
+
+    pub fn to_parts(&self) -> Vec<NamePart> {
+        let mut holes = self.holes.iter().peekable();
+        let mut parts = vec![];
+        for index in 0..=self.parts.len() {
+            while holes.next_if(|&&hole| hole == index).is_some() {
+                parts.push(NamePart::Hole);
+            }
+            if let Some(word) = self.parts.get(index) {
+                parts.push(NamePart::Word(word.clone()));
+            }
+        }
+        parts
+    }
+
+    /// An argument that comes before every word, as added by a dot.
+    pub fn with_leading_hole(&self) -> MixfixName {
+        MixfixName {
+            parts: self.parts.clone(),
+            holes: Some(0).into_iter().chain(self.holes.clone()).collect(),
+        }
+    }
+
+    /// An argument that comes after every word, as added by a colon.
+    pub fn with_trailing_hole(&self) -> MixfixName {
+        MixfixName {
+            parts: self.parts.clone(),
+            holes: self
+                .holes
+                .iter()
+                .copied()
+                .chain(Some(self.parts.len()))
+                .collect(),
+        }
+    }
+
+    /// `_greater-than_`: an underscore for every hole and a hyphen between
+    /// adjacent words, while `_`, `-` and `\` within words are escaped by a
+    /// backslash, so that [`MixfixName::decode`] gives the name back.
+    pub fn encode(&self) -> String {
+        let mut encoded = String::new();
+        let mut previous_was_word = false;
+        for part in self.to_parts() {
+            match part {
+                NamePart::Word(word) => {
+                    if previous_was_word {
+                        encoded.push('-');
+                    }
+                    for character in word.chars() {
+                        if ['_', '-', '\\'].contains(&character) {
+                            encoded.push('\\');
+                        }
+                        encoded.push(character);
+                    }
+                    previous_was_word = true;
+                }
+                NamePart::Hole => {
+                    encoded.push('_');
+                    previous_was_word = false;
+                }
+            }
+        }
+        encoded
+    }
+
+    pub fn decode(encoded: &str) -> Option<MixfixName> {
+        let mut parts = vec![];
+        let mut word: Option<String> = None;
+        let mut characters = encoded.chars();
+        while let Some(character) = characters.next() {
+            match character {
+                '\\' => word
+                    .get_or_insert_with(String::new)
+                    .push(characters.next()?),
+                '_' => {
+                    parts.extend(word.take().map(NamePart::Word));
+                    parts.push(NamePart::Hole);
+                }
+                // A hyphen only ever separates two words
+                '-' => {
+                    parts.push(NamePart::Word(word.take()?));
+                    if matches!(characters.clone().next(), None | Some('_' | '-')) {
+                        return None;
+                    }
+                }
+                character => word.get_or_insert_with(String::new).push(character),
+            }
+        }
+        parts.extend(word.map(NamePart::Word));
+        Some(MixfixName::from_parts(&parts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mangle() {
+        let parts = [
+            NamePart::Hole,
+            NamePart::Word("greater".to_string()),
+            NamePart::Word("than".to_string()),
+            NamePart::Hole,
+        ];
+        assert_eq!(NameMangler::Hyphen.mangle(&parts), "greater-than");
+        assert_eq!(NameMangler::Mixfix.mangle(&parts), "_greater-than_");
+        assert_eq!(NameMangler::CamelCase.mangle(&parts), "greaterThan");
+        assert_eq!(NameMangler::SnakeCase.mangle(&parts), "greater_than");
+
+        let operators = [
+            NamePart::Hole,
+            NamePart::Word("-".to_string()),
+            NamePart::Word("+".to_string()),
+            NamePart::Hole,
+        ];
+        assert_eq!(NameMangler::Hyphen.mangle(&operators), "-.+");
+        assert_eq!(NameMangler::Mixfix.mangle(&operators), "_-.+_");
+        assert_eq!(NameMangler::CamelCase.mangle(&operators), "-.+");
+        assert_eq!(NameMangler::SnakeCase.mangle(&operators), "-.+");
+    }
+
+    #[test]
+    fn mixfix_name_encoding_is_reversible() {
+        let names = [
+            MixfixName {
+                parts: vec!["greater".to_string(), "than".to_string()],
+                holes: vec![0, 2],
+            },
+            MixfixName {
+                parts: vec!["-".to_string()],
+                holes: vec![0, 1],
+            },
+            MixfixName {
+                parts: vec!["my_var".to_string(), "\\".to_string()],
+                holes: vec![1, 1],
+            },
+        ];
+        let encodings = names.iter().map(MixfixName::encode).collect::<Vec<_>>();
+        assert_eq!(encodings, ["_greater-than_", "_\\-_", "my\\_var__\\\\"]);
+        for (name, encoded) in names.iter().zip(&encodings) {
+            assert_eq!(MixfixName::decode(encoded).as_ref(), Some(name));
+        }
+        assert_eq!(MixfixName::decode("a--b"), None);
+        assert_eq!(MixfixName::decode("a-"), None);
+        assert_eq!(MixfixName::decode("a\\"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use num_bigint::BigInt;
+
+/// The value of a number literal. Integers are exact whatever their size,
+/// a literal with a fraction or an exponent is a float.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Integer(BigInt),
+    Float(f64),
+}
+
+impl Number {
+    /// Reads a literal as the tokenizer delimits it: decimal digits with an
+    /// optional fraction and exponent, like `1_000`, `1.5` or `1e-9`, or an
+    /// integer in another base, like `0xff`, `0b1010` or `0o17`, either
+    /// after an optional `-`.
+    pub(crate) fn from_literal(text: &str) -> Result<Self, String> {
+        if let Some(magnitude) = text.strip_prefix('-') {
+            return Self::from_literal(magnitude).map(|number| number.negate());
+        }
+        let (radix, digits) = match text.get(..2) {
+            Some("0x") => (16, &text[2..]),
+            Some("0b") => (2, &text[2..]),
+            Some("0o") => (8, &text[2..]),
+            _ => (10, text),
+        };
+        if digits.is_empty() {
+            return Err(format!("missing digits after `{}`", text));
+        }
+        let characters = digits.chars().collect::<Vec<_>>();
+        let is_digit = |index: Option<usize>| {
+            index
+                .and_then(|index| characters.get(index))
+                .is_some_and(|character| character.is_digit(radix))
+        };
+        if (0..characters.len()).any(|index| {
+            characters[index] == '_'
+                && !(is_digit(index.checked_sub(1)) && is_digit(Some(index + 1)))
+        }) {
+            return Err("`_` separates digits, so it needs a digit on both sides".to_string());
+        }
+
+        let digits = digits.replace('_', "");
+        if radix != 10 || digits.chars().all(|character| character.is_ascii_digit()) {
+            return BigInt::parse_bytes(digits.as_bytes(), radix)
+                .map(Number::Integer)
+                .ok_or_else(|| format!("`{}` has digits outside of base {}", text, radix));
+        }
+        match digits.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(Number::Float(value)),
+            Ok(_) => Err("number is too large for a float".to_string()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    pub fn negate(&self) -> Self {
+        match self {
+            Number::Integer(value) => Number::Integer(-value),
+            Number::Float(value) => Number::Float(-value),
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    /// Integers in decimal, floats in the shortest form that reads back the
+    /// same and always with a fraction or an exponent, like `1.0` or `1e-9`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Integer(value) => write!(f, "{}", value),
+            Number::Float(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Result<String, String> {
+        Number::from_literal(text).map(|number| number.to_string())
+    }
+
+    #[test]
+    fn reads_integers_and_floats() {
+        for (text, expected) in [
+            ("1", "1"),
+            ("1.0", "1.0"),
+            ("10000000000000001", "10000000000000001"),
+            (
+                "123456789012345678901234567890",
+                "123456789012345678901234567890",
+            ),
+            ("1_000_000", "1000000"),
+            ("0xff", "255"),
+            ("0xFF_FF", "65535"),
+            ("0b1010", "10"),
+            ("0o17", "15"),
+            ("1e-9", "1e-9"),
+            ("1.5E3", "1500.0"),
+            ("2e+2", "200.0"),
+        ] {
+            assert_eq!(read(text).as_deref(), Ok(expected), "reading {}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_literals() {
+        for text in ["0x", "0b102", "0o8", "1__0", "1_", "1e999"] {
+            assert!(read(text).is_err(), "reading {}", text);
+        }
+    }
+}
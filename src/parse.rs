@@ -1,100 +1,355 @@
+use std::collections::HashMap;
+
 use itertools::Itertools as _;
 
 use crate::{
-    lexpr::Lexpr,
-    tokenizer::{Span, Token, TokenKind, Tokenizer},
+    cst::{NodeShape, SyntaxKind},
+    name::{MixfixName, NameMangler, NamePart},
+    precedence::{self, Fixity},
+    sexp::{LiteralName, LiteralNumber, LiteralString, Sexp, SexpList, SexpMap},
+    tokenizer::{Position, Span, Token, TokenKind, TokenizeError, Tokenizer},
 };
 
+/// The tokens of an error are boxed, which keeps results small.
 #[derive(Debug)]
-enum ParseError {
-    TokenizeError(crate::tokenizer::TokenizeError),
+pub enum ParseError {
+    TokenizeError(TokenizeError),
     UnexpectedToken {
-        token: Token,
-        expected: Option<TokenKind>,
+        token: Box<Token>,
+        expected: Option<Box<TokenKind>>,
+        /// The token right before the unexpected one.
+        previous: Option<Box<TokenKind>>,
+        /// Whether layout closed the form before the token, see
+        /// [`ParseOptions::layout`].
+        layout_closed: bool,
     },
+    /// The input, or the current form under layout, ended too early.
     UnexpectedEof {
-        expected: Option<TokenKind>,
+        expected: Option<Box<TokenKind>>,
+        previous: Option<Box<TokenKind>>,
+        /// An empty span where the form ended.
+        span: Span,
+    },
+    /// An entry of `{...}` that is not a `key: value` pair, spanning the entry.
+    MapEntryWithoutValue {
+        span: Span,
     },
 }
 
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::TokenizeError(error) => error.span(),
+            ParseError::UnexpectedToken { token, .. } => token.span,
+            ParseError::UnexpectedEof { span, .. } => *span,
+            ParseError::MapEntryWithoutValue { span } => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = match self {
+            ParseError::TokenizeError(error) => return write!(f, "{}", error),
+            ParseError::UnexpectedToken {
+                token, expected, ..
+            } => {
+                write!(f, "unexpected {}", token.kind)?;
+                expected
+            }
+            ParseError::UnexpectedEof { expected, .. } => {
+                write!(f, "unexpected end of input")?;
+                expected
+            }
+            ParseError::MapEntryWithoutValue { .. } => {
+                return write!(f, "map entry without a value, expected `key: value`")
+            }
+        };
+        match expected {
+            Some(expected) => write!(f, ", expected {}", expected),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 type ParseResult<T> = Result<T, ParseError>;
 
+/// Parses every form of `input_text` into S-expressions.
+pub fn parse_str(input_text: &str) -> Result<Vec<Sexp>, ParseError> {
+    Parser::new(input_text).parse_sexps()
+}
+
+pub fn parse_str_with_options(
+    input_text: &str,
+    options: ParseOptions,
+) -> Result<Vec<Sexp>, ParseError> {
+    Parser::with_options(input_text, options).parse_sexps()
+}
+
+/// Parses every form of `input_text` like [`parse_str_with_options`], and
+/// returns the comments between the tokens too, in source order.
+pub fn parse_str_with_trivia(
+    input_text: &str,
+    options: ParseOptions,
+) -> Result<(Vec<Sexp>, Vec<Token>), ParseError> {
+    let mut parser = Parser::with_options(input_text, options);
+    let sexps = parser.parse_sexps()?;
+    Ok((sexps, parser.trivia().to_vec()))
+}
+
+/// Parses every form of `input_text`, carrying on after errors: a form that
+/// fails to parse is skipped up to the next `,`, closing bracket or layout
+/// boundary and becomes a [`Sexp::Error`]. Returns the forms together with
+/// every error found, in source order.
+pub fn parse_str_recovering(
+    input_text: &str,
+    options: ParseOptions,
+) -> (Vec<Sexp>, Vec<ParseError>) {
+    let (list, errors) = parse_list_recovering(input_text, options.clone());
+    let sexps = list.to_sexps(&options);
+    (sexps, errors)
+}
+
+/// The shape of the syntax tree of every form of `input_text`, which the
+/// [`crate::cst`] fills in with tokens.
+pub(crate) fn parse_node_shapes(
+    input_text: &str,
+    options: ParseOptions,
+) -> (Vec<NodeShape>, Vec<ParseError>) {
+    let (list, errors) = parse_list_recovering(input_text, options);
+    (
+        list.0.iter().map(RightAssocExpr::node_shape).collect(),
+        errors,
+    )
+}
+
+fn parse_list_recovering(input_text: &str, options: ParseOptions) -> (List, Vec<ParseError>) {
+    let mut parser = Parser::with_options(input_text, options);
+    parser.recovering = true;
+    let list = parser.parse_document().unwrap_or_else(|error| {
+        parser.errors.push(error);
+        List(vec![])
+    });
+    let mut errors = parser.errors;
+    errors.sort_by_key(|error| error.span().start.character_index);
+    // A form cut short is reported by each list it ends, keep the outermost
+    // report, which names the missing closing bracket.
+    errors.dedup_by(|later, earlier| {
+        let same_position = later.span().start == earlier.span().start;
+        if same_position {
+            std::mem::swap(later, earlier);
+        }
+        same_position
+    });
+    (list, errors)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Off-side rule: outside of brackets, a form continues over the lines
+    /// indented deeper than the line it started on, and the first line
+    /// indented at or left of that line closes the form, exactly where a
+    /// closing parenthesis would. The block after a colon closes the same
+    /// way at the first line indented at or left of the colon's line, and
+    /// the lines after it that the form goes on with are further arguments,
+    /// so `f:\n  g:\n    x\n  y` is `(f (g x) y)`.
+    pub layout: bool,
+    /// Outside of brackets, a line that starts at the same indentation as
+    /// the previous form separates the two like a comma, so a document can
+    /// hold one form per line.
+    pub newline_as_comma: bool,
+    pub name_mangler: NameMangler,
+    pub unary_operators: UnaryOperators,
+    /// Binary operators grouped by precedence, like `a + b * c` as
+    /// `(+ a (* b c))`, see [`crate::arithmetic_operators`]. A chain with an
+    /// operator missing from the table stays one mixfix call, which is the
+    /// translation of every chain when the table is empty.
+    pub operators: HashMap<String, Fixity>,
+    pub collections: Collections,
+}
+
+/// How `[a, b]` and `{k: v, ...}` translate, parentheses always only group
+/// or list.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Collections {
+    /// [`Sexp::Vector`] and [`Sexp::Map`], which printers write in the
+    /// literal syntax of their dialect, like EDN.
+    #[default]
+    Literals,
+    /// Lists tagged by their kind, `(vector a b)` and `(map k v ...)`, for
+    /// dialects without such literals.
+    Tagged,
+}
+
+/// How an operator written next to its single argument translates, like
+/// the prefix `- x`, the postfix `n !` or the negative literal `-1`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UnaryOperators {
+    /// Like any other call: `- x` is `(- x)` and `n !` is `(! n)` with names
+    /// from the name mangler, so `(-_ x)` and `(_! n)` with mixfix names, and
+    /// `-1` is a negative number.
+    #[default]
+    Merged,
+    /// Calls of the bare operator whatever the name mangler, with a prefix
+    /// minus spelled `neg` to tell it from subtraction: `-1` and `- x` are
+    /// `(neg 1)` and `(neg x)`, `n !` is `(! n)`.
+    Neg,
+}
+
+/// The layout boundary of the form or colon block being parsed, see
+/// [`ParseOptions::layout`].
+#[derive(Debug, Clone, Copy)]
+struct LayoutFence {
+    line_number: usize,
+    indentation: usize,
+}
+
 struct Parser<'a> {
     tokenizer: Tokenizer<'a>,
+    options: ParseOptions,
+    /// Comments skipped so far, in source order.
+    trivia: Vec<Token>,
+    /// Line of the last consumed token, used to tell whether the next token starts a line.
+    last_line_number: usize,
+    /// Column of the first token on the line of the last consumed token.
+    line_indentation: usize,
+    bracket_depth: usize,
+    /// The fence of the current top-level form, then one per colon block
+    /// opened in it.
+    layout_fences: Vec<LayoutFence>,
+    previous_token_kind: Option<TokenKind>,
+    /// Whether errors are collected into `errors` instead of aborting.
+    recovering: bool,
+    errors: Vec<ParseError>,
+    /// The stand-in for a malformed token when recovering, which is next
+    /// instead of the tokenizer's next token.
+    malformed: Option<Token>,
 }
 
 #[derive(Debug, Clone)]
 struct List(Vec<RightAssocExpr>);
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 enum RightAssocExpr {
     RightAssocExpr {
         left: LeftAssocExpr,
-        colon: Token,
         right: Box<RightAssocExpr>,
+        /// Further arguments on the lines after a colon block that layout
+        /// closed, see [`ParseOptions::layout`].
+        continued: Vec<RightAssocExpr>,
     },
     LeftAssocExpr(LeftAssocExpr),
+    /// The skipped tokens of a form that failed to parse.
+    Error(Span),
 }
 
-#[derive(Debug, Clone)]
-enum Sexp {
-    List(Vec<Sexp>),
-    Number(LiteralNumber),
-    String(LiteralString),
-    Name(LiteralName),
-}
-
-impl Sexp {
-    fn stringify(&self) -> String {
+impl RightAssocExpr {
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
         match self {
-            Sexp::List(exprs) => {
-                format!("({})", exprs.iter().map(|expr| expr.stringify()).join(" "))
+            RightAssocExpr::RightAssocExpr {
+                left,
+                right,
+                continued,
+                ..
+            } => {
+                // The words before a colon always name the call, so
+                // `(x) greater than: y` is `(greater-than x y)`.
+                let left = match left.function_call_like() {
+                    Some(call) => call.mixfix_to_sexp(options),
+                    None => left.to_sexp(options),
+                };
+                let (head, tail) = match left {
+                    Sexp::List(list) => match list.exprs().split_first() {
+                        Some((head, tail)) => (head.clone(), tail.to_vec()),
+                        None => return Sexp::List(list),
+                    },
+                    left => (left, vec![]),
+                };
+                let arguments = right
+                    .colon_arguments(options)
+                    .into_iter()
+                    .chain(continued.iter().map(|expr| expr.to_sexp(options)))
+                    .collect_vec();
+                let head = arguments.iter().fold(head, |head, _| {
+                    with_hole(&head, MixfixName::with_trailing_hole, options)
+                });
+                Sexp::List(SexpList::new(
+                    Some(head)
+                        .into_iter()
+                        .chain(tail)
+                        .chain(arguments)
+                        .collect(),
+                    self.span(),
+                ))
             }
-            Sexp::Number(number) => format!("{}", number.value),
-            Sexp::String(string) => format!("{:#?}", string.value),
-            Sexp::Name(name) => format!("{}", name.value),
+            RightAssocExpr::LeftAssocExpr(expr) => expr.to_sexp(options),
+            RightAssocExpr::Error(span) => Sexp::Error(*span),
         }
     }
-}
 
-#[derive(Debug, Clone)]
-struct LiteralString {
-    value: String,
-    span: Span,
-}
-
-#[derive(Debug, Clone)]
-struct LiteralName {
-    value: String,
-    span: Span,
-}
+    /// The arguments the expression after a colon passes. Merged words name
+    /// no call there, so `f: x y` is `(f x-y)`, and a call without words
+    /// passes each of its arguments, so `f: (x) (y)` is `(f x y)`.
+    fn colon_arguments(&self, options: &ParseOptions) -> Vec<Sexp> {
+        let call = match self {
+            RightAssocExpr::LeftAssocExpr(expr) => expr.function_call_like(),
+            _ => None,
+        };
+        let Some(call) = call else {
+            return vec![self.to_sexp(options)];
+        };
+        let exprs = Some(&call.head).into_iter().chain(&call.tail).collect_vec();
+        let words = exprs
+            .iter()
+            .filter_map(|expr| match expr {
+                AtomicExpr::Name(name) => Some(name),
+                _ => None,
+            })
+            .collect_vec();
+        match merged_name(&words, options) {
+            Some(name) if words.len() == exprs.len() => vec![name],
+            Some(_) => vec![call.to_sexp(options)],
+            None => exprs.iter().map(|expr| expr.to_sexp(options)).collect(),
+        }
+    }
 
-#[derive(Debug, Clone)]
-struct LiteralNumber {
-    value: f64,
-    span: Span,
-}
+    fn span(&self) -> Span {
+        match self {
+            RightAssocExpr::RightAssocExpr {
+                left,
+                right,
+                continued,
+                ..
+            } => left.span().join(&continued.last().unwrap_or(right).span()),
+            RightAssocExpr::LeftAssocExpr(expr) => expr.span(),
+            RightAssocExpr::Error(span) => *span,
+        }
+    }
 
-impl RightAssocExpr {
-    fn to_sexp(&self) -> Sexp {
+    fn node_shape(&self) -> NodeShape {
         match self {
-            RightAssocExpr::RightAssocExpr { left, colon, right } => {
-                let left = left.to_sexp();
-                let right = right.to_sexp();
-                match left {
-                    Sexp::List(exprs) => match exprs.split_first() {
-                        Some((head, tail)) => Sexp::List(
-                            Some(head.clone())
-                                .into_iter()
-                                .chain(tail.to_vec())
-                                .chain(Some(right))
-                                .collect(),
-                        ),
-                        None => Sexp::List(exprs),
-                    },
-                    _ => Sexp::List([left, right].to_vec()),
-                }
-            }
-            RightAssocExpr::LeftAssocExpr(expr) => expr.to_sexp(),
+            RightAssocExpr::RightAssocExpr {
+                left,
+                right,
+                continued,
+                ..
+            } => NodeShape::new(
+                SyntaxKind::Colon,
+                self.span(),
+                Some(left.node_shape())
+                    .into_iter()
+                    .chain(
+                        Some(right.as_ref())
+                            .into_iter()
+                            .chain(continued)
+                            .map(Self::node_shape),
+                    )
+                    .collect(),
+            ),
+            RightAssocExpr::LeftAssocExpr(expr) => expr.node_shape(),
+            RightAssocExpr::Error(span) => NodeShape::new(SyntaxKind::Error, *span, vec![]),
         }
     }
 }
@@ -103,32 +358,72 @@ impl RightAssocExpr {
 enum LeftAssocExpr {
     LeftAssocExpr {
         left: Box<LeftAssocExpr>,
-        dot: Token,
         right: OperatorFunctionCallLike,
     },
     OperatorFunctionCallLike(OperatorFunctionCallLike),
 }
 impl LeftAssocExpr {
-    fn to_sexp(&self) -> Sexp {
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
         match self {
-            LeftAssocExpr::LeftAssocExpr { left, dot, right } => {
-                let right = right.to_sexp();
-                let left = left.to_sexp();
+            LeftAssocExpr::LeftAssocExpr { left, right, .. } => {
+                let right = right.to_sexp(options);
+                let left = left.to_sexp(options);
                 match right {
-                    Sexp::List(exprs) => match exprs.split_first() {
-                        Some((head, tail)) => Sexp::List(
-                            Some(head.clone())
+                    Sexp::List(list) => match list.exprs().split_first() {
+                        Some((head, tail)) => Sexp::List(SexpList::new(
+                            Some(with_hole(head, MixfixName::with_leading_hole, options))
                                 .into_iter()
                                 .chain(Some(left))
                                 .chain(tail.to_vec())
                                 .collect(),
-                        ),
+                            self.span(),
+                        )),
                         None => left,
                     },
-                    _ => Sexp::List([right, left].to_vec()),
+                    _ => Sexp::List(SexpList::new(
+                        [
+                            with_hole(&right, MixfixName::with_leading_hole, options),
+                            left,
+                        ]
+                        .to_vec(),
+                        self.span(),
+                    )),
                 }
             }
-            LeftAssocExpr::OperatorFunctionCallLike(expr) => expr.to_sexp(),
+            LeftAssocExpr::OperatorFunctionCallLike(expr) => expr.to_sexp(options),
+        }
+    }
+
+    /// The call of atomic expressions this expression is, when it is nothing
+    /// more.
+    fn function_call_like(&self) -> Option<&FunctionCallLike> {
+        match self {
+            LeftAssocExpr::OperatorFunctionCallLike(OperatorFunctionCallLike {
+                head:
+                    OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(
+                        AlphanumericFunctionCallLike::FunctionCallLike(call),
+                    ),
+                tail,
+            }) if tail.is_empty() => Some(call),
+            _ => None,
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            LeftAssocExpr::LeftAssocExpr { left, right, .. } => left.span().join(&right.span()),
+            LeftAssocExpr::OperatorFunctionCallLike(expr) => expr.span(),
+        }
+    }
+
+    fn node_shape(&self) -> NodeShape {
+        match self {
+            LeftAssocExpr::LeftAssocExpr { left, right, .. } => NodeShape::new(
+                SyntaxKind::Dot,
+                self.span(),
+                vec![left.node_shape(), right.node_shape()],
+            ),
+            LeftAssocExpr::OperatorFunctionCallLike(expr) => expr.node_shape(),
         }
     }
 }
@@ -152,6 +447,17 @@ impl OperatorFunctionCallLikeComponent {
             }
         }
     }
+
+    fn node_shape(&self) -> NodeShape {
+        match self {
+            OperatorFunctionCallLikeComponent::Operator(operator) => {
+                NodeShape::new(SyntaxKind::Atom, operator.span(), vec![])
+            }
+            OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(function_call_like) => {
+                function_call_like.node_shape()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -165,64 +471,258 @@ impl Operator {
     }
 }
 impl OperatorFunctionCallLike {
-    fn to_sexp(&self) -> Sexp {
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
         if self.tail.is_empty() {
             match &self.head {
-                OperatorFunctionCallLikeComponent::Operator(operator) => Sexp::Name(LiteralName {
-                    value: operator.representation.clone(),
-                    span: operator.span,
-                }),
+                OperatorFunctionCallLikeComponent::Operator(operator) => Sexp::Name(
+                    LiteralName::new(operator.representation.clone(), operator.span),
+                ),
                 OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(
                     function_call_like,
-                ) => function_call_like.to_sexp(),
+                ) => function_call_like.to_sexp(options),
             }
+        } else if let Some(grouped) = self.group(options) {
+            grouped
         } else {
-            let iter = Some(&self.head).into_iter().chain(self.tail.iter());
-            let name = iter
-                .clone()
+            let (parts, arguments) = Some(&self.head)
+                .into_iter()
+                .chain(self.tail.iter())
                 .map(|component| match component {
                     OperatorFunctionCallLikeComponent::Operator(operator) => {
-                        operator.representation.clone()
+                        (NamePart::Word(operator.representation.clone()), None)
                     }
-                    _ => "_".to_string(),
-                })
-                .join("");
-            let arguments = iter
-                .filter_map(|expr| match expr {
                     OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(
                         function_call_like,
-                    ) => Some(function_call_like.to_sexp()),
-                    _ => None,
+                    ) => (NamePart::Hole, Some(function_call_like.to_sexp(options))),
                 })
-                .collect_vec();
-            Sexp::List(
-                [Sexp::Name(LiteralName {
-                    value: name,
-                    span: self
-                        .tail
-                        .last()
-                        .map(|last| self.head.span().join(&last.span()))
-                        .unwrap_or(self.head.span()),
-                })]
+                .unzip::<_, _, Vec<_>, Vec<_>>();
+            let name_span = Some(&self.head)
+                .into_iter()
+                .chain(self.tail.iter())
+                .filter_map(|component| match component {
+                    OperatorFunctionCallLikeComponent::Operator(operator) => Some(operator.span),
+                    OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(_) => None,
+                })
+                .reduce(|first, last| first.join(&last));
+            call_to_sexp(
+                parts,
+                arguments.into_iter().flatten().collect(),
+                name_span,
+                self.span(),
+                options,
+            )
+        }
+    }
+
+    /// Nested binary calls by [`ParseOptions::operators`], for a chain that
+    /// alternates operands and operators.
+    fn group(&self, options: &ParseOptions) -> Option<Sexp> {
+        let mut operands = vec![];
+        let mut operators = vec![];
+        for (index, component) in Some(&self.head)
+            .into_iter()
+            .chain(self.tail.iter())
+            .enumerate()
+        {
+            match component {
+                OperatorFunctionCallLikeComponent::AlphanumericFunctionCallLike(
+                    function_call_like,
+                ) if index % 2 == 0 => operands.push(function_call_like.to_sexp(options)),
+                OperatorFunctionCallLikeComponent::Operator(operator) if index % 2 == 1 => {
+                    operators.push((operator.representation.as_str(), operator))
+                }
+                _ => return None,
+            }
+        }
+        if operators.is_empty() || operands.len() == operators.len() {
+            return None;
+        }
+        precedence::group(
+            operands,
+            operators,
+            &options.operators,
+            |operator, left, right| {
+                let span = left.span().join(&right.span());
+                call_to_sexp(
+                    vec![
+                        NamePart::Hole,
+                        NamePart::Word(operator.representation.clone()),
+                        NamePart::Hole,
+                    ],
+                    vec![left, right],
+                    Some(operator.span),
+                    span,
+                    options,
+                )
+            },
+        )
+    }
+
+    fn span(&self) -> Span {
+        match self.tail.last() {
+            Some(tail) => self.head.span().join(&tail.span()),
+            None => self.head.span(),
+        }
+    }
+
+    fn node_shape(&self) -> NodeShape {
+        if self.tail.is_empty() {
+            self.head.node_shape()
+        } else {
+            NodeShape::new(
+                SyntaxKind::OperatorCall,
+                self.span(),
+                Some(&self.head)
+                    .into_iter()
+                    .chain(self.tail.iter())
+                    .map(OperatorFunctionCallLikeComponent::node_shape)
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// The call of a merged name, when no word is left to name the call, the
+/// first argument takes the head position, so `(f) (x)` is `(f x)`.
+///
+/// `name_span` runs from the first to the last word, `None` when there is no
+/// word, and `span` covers the whole call.
+fn call_to_sexp(
+    parts: Vec<NamePart>,
+    arguments: Vec<Sexp>,
+    name_span: Option<Span>,
+    span: Span,
+    options: &ParseOptions,
+) -> Sexp {
+    if let Some(name_span) = name_span {
+        let name = LiteralName::with_mixfix(
+            unary_name(&parts, options).unwrap_or_else(|| options.name_mangler.mangle(&parts)),
+            name_span,
+            MixfixName::from_parts(&parts),
+        );
+        Sexp::List(SexpList::new(
+            Some(Sexp::Name(name))
                 .into_iter()
                 .chain(arguments)
                 .collect(),
-            )
+            span,
+        ))
+    } else {
+        let mut arguments = arguments.into_iter();
+        let head = arguments.next().map(|head| {
+            arguments.clone().fold(head, |head, _| {
+                with_hole(&head, MixfixName::with_trailing_hole, options)
+            })
+        });
+        Sexp::List(SexpList::new(
+            head.into_iter().chain(arguments).collect(),
+            span,
+        ))
+    }
+}
+
+/// Words that merge into a name without naming a call, like the argument in
+/// `f: x y`.
+/// `None` without words.
+fn merged_name(words: &[&LiteralName], options: &ParseOptions) -> Option<Sexp> {
+    let span = words
+        .iter()
+        .map(|word| word.span())
+        .reduce(|first, last| first.join(&last))?;
+    let parts = words
+        .iter()
+        .map(|word| NamePart::Word(word.value().to_string()))
+        .collect_vec();
+    Some(Sexp::Name(LiteralName::new(
+        options.name_mangler.mangle(&parts),
+        span,
+    )))
+}
+
+/// Records the argument that a dot or colon gives to the call headed by
+/// `head`, a plain name becomes the one word of its call.
+fn with_hole(head: &Sexp, add_hole: fn(&MixfixName) -> MixfixName, options: &ParseOptions) -> Sexp {
+    let Sexp::Name(name) = head else {
+        return head.clone();
+    };
+    let mixfix = match name.mixfix() {
+        Some(mixfix) => add_hole(mixfix),
+        None => add_hole(&MixfixName::from_parts(&[NamePart::Word(
+            name.value().to_string(),
+        )])),
+    };
+    let parts = mixfix.to_parts();
+    Sexp::Name(LiteralName::with_mixfix(
+        unary_name(&parts, options).unwrap_or_else(|| options.name_mangler.mangle(&parts)),
+        name.span(),
+        mixfix,
+    ))
+}
+
+/// The bare operator naming a call of one operator and one argument, see
+/// [`UnaryOperators::Neg`].
+fn unary_name(parts: &[NamePart], options: &ParseOptions) -> Option<String> {
+    if options.unary_operators != UnaryOperators::Neg {
+        return None;
+    }
+    let is_operator = |word: &str| {
+        !word.starts_with(|character: char| character.is_alphanumeric() || character == '_')
+    };
+    match parts {
+        [NamePart::Word(operator), NamePart::Hole] if operator == "-" => Some("neg".to_string()),
+        [NamePart::Word(operator), NamePart::Hole] | [NamePart::Hole, NamePart::Word(operator)]
+            if is_operator(operator) =>
+        {
+            Some(operator.clone())
         }
+        _ => None,
     }
 }
+
+/// `-1` as `(neg 1)`, see [`UnaryOperators::Neg`]. The name spans the sign
+/// and the number the digits.
+fn negation(number: &LiteralNumber) -> Sexp {
+    let span = number.span();
+    let sign_end = Position {
+        column_number: span.start.column_number + 1,
+        character_index: span.start.character_index + 1,
+        byte_index: span.start.byte_index + 1,
+        ..span.start
+    };
+    let sign = LiteralName::with_mixfix(
+        "neg".to_string(),
+        Span {
+            start: span.start,
+            end: sign_end,
+        },
+        MixfixName::from_parts(&[NamePart::Word("-".to_string()), NamePart::Hole]),
+    );
+    let magnitude = LiteralNumber::new(
+        number.value().negate(),
+        number.text()[1..].to_string(),
+        Span {
+            start: sign_end,
+            end: span.end,
+        },
+    );
+    Sexp::List(SexpList::new(
+        vec![Sexp::Name(sign), Sexp::Number(magnitude)],
+        span,
+    ))
+}
+
 #[derive(Debug, Clone)]
 enum AlphanumericFunctionCallLike {
     FunctionCallLike(FunctionCallLike),
     AtomicExpr(AtomicExpr),
 }
 impl AlphanumericFunctionCallLike {
-    fn to_sexp(&self) -> Sexp {
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
         match self {
             AlphanumericFunctionCallLike::FunctionCallLike(function_call_like) => {
-                function_call_like.to_sexp()
+                function_call_like.to_sexp(options)
             }
-            AlphanumericFunctionCallLike::AtomicExpr(expr) => expr.to_sexp(),
+            AlphanumericFunctionCallLike::AtomicExpr(expr) => expr.to_sexp(options),
         }
     }
 
@@ -234,6 +734,15 @@ impl AlphanumericFunctionCallLike {
             AlphanumericFunctionCallLike::AtomicExpr(expr) => expr.span(),
         }
     }
+
+    fn node_shape(&self) -> NodeShape {
+        match self {
+            AlphanumericFunctionCallLike::FunctionCallLike(function_call_like) => {
+                function_call_like.node_shape()
+            }
+            AlphanumericFunctionCallLike::AtomicExpr(expr) => expr.node_shape(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -242,33 +751,71 @@ struct FunctionCallLike {
     tail: Vec<AtomicExpr>,
 }
 impl FunctionCallLike {
-    fn to_sexp(&self) -> Sexp {
-        let iter = Some(&self.head).into_iter().chain(self.tail.iter());
-        let name = iter
-            .clone()
+    /// A call that starts with parentheses and ends with words is headed by
+    /// the parenthesized expression, and the words merge into its last
+    /// argument, so `(f) x y` is `(f x-y)`. Any other call is named by its
+    /// words, see [`FunctionCallLike::mixfix_to_sexp`].
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
+        match self.headed_call(options) {
+            Some(call) => call,
+            None => self.mixfix_to_sexp(options),
+        }
+    }
+
+    fn headed_call(&self, options: &ParseOptions) -> Option<Sexp> {
+        let AtomicExpr::Parenthesized(head) = &self.head else {
+            return None;
+        };
+        if head.open.kind != TokenKind::LeftParenthesis {
+            return None;
+        }
+        let words_start = self
+            .tail
+            .iter()
+            .position(|expr| matches!(expr, AtomicExpr::Name(_)))?;
+        let (arguments, words) = self.tail.split_at(words_start);
+        let words = words
+            .iter()
             .map(|expr| match expr {
-                AtomicExpr::Name(name) => name.value.clone(),
-                _ => "_".to_string(),
+                AtomicExpr::Name(name) => Some(name),
+                _ => None,
             })
-            .join("");
-        let arguments = iter
-            .filter_map(|expr| match expr {
-                AtomicExpr::Name(_) => None,
-                _ => Some(expr.to_sexp()),
+            .collect::<Option<Vec<_>>>()?;
+        Some(Sexp::List(SexpList::new(
+            Some(head.to_sexp(options))
+                .into_iter()
+                .chain(arguments.iter().map(|argument| argument.to_sexp(options)))
+                .chain(Some(merged_name(&words, options)?))
+                .collect(),
+            self.span(),
+        )))
+    }
+
+    /// The call named by the merged words, with the other expressions as
+    /// arguments in the holes between them.
+    fn mixfix_to_sexp(&self, options: &ParseOptions) -> Sexp {
+        let (parts, arguments) = Some(&self.head)
+            .into_iter()
+            .chain(self.tail.iter())
+            .map(|expr| match expr {
+                AtomicExpr::Name(name) => (NamePart::Word(name.value().to_string()), None),
+                _ => (NamePart::Hole, Some(expr.to_sexp(options))),
             })
-            .collect_vec();
-        Sexp::List(
-            [Sexp::Name(LiteralName {
-                value: name,
-                span: self
-                    .tail
-                    .last()
-                    .map(|last| self.head.span().join(&last.span()))
-                    .unwrap_or(self.head.span()),
-            })]
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        let name_span = Some(&self.head)
             .into_iter()
-            .chain(arguments)
-            .collect(),
+            .chain(self.tail.iter())
+            .filter_map(|expr| match expr {
+                AtomicExpr::Name(name) => Some(name.span()),
+                _ => None,
+            })
+            .reduce(|first, last| first.join(&last));
+        call_to_sexp(
+            parts,
+            arguments.into_iter().flatten().collect(),
+            name_span,
+            self.span(),
+            options,
         )
     }
 
@@ -278,6 +825,18 @@ impl FunctionCallLike {
             None => self.head.span(),
         }
     }
+
+    fn node_shape(&self) -> NodeShape {
+        NodeShape::new(
+            SyntaxKind::Call,
+            self.span(),
+            Some(&self.head)
+                .into_iter()
+                .chain(self.tail.iter())
+                .map(AtomicExpr::node_shape)
+                .collect(),
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -286,25 +845,52 @@ enum AtomicExpr {
     Number(LiteralNumber),
     Parenthesized(ParenthesizedExpr),
     Name(LiteralName),
+    /// A malformed token, see [`TokenKind::Malformed`].
+    Error(Span),
 }
 impl AtomicExpr {
-    fn to_sexp(&self) -> Sexp {
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
         match self {
             AtomicExpr::String(string) => Sexp::String(string.clone()),
+            AtomicExpr::Number(number)
+                if options.unary_operators == UnaryOperators::Neg
+                    && number.text().starts_with('-') =>
+            {
+                negation(number)
+            }
             AtomicExpr::Number(number) => Sexp::Number(number.clone()),
-            AtomicExpr::Parenthesized(expr) => expr.list.to_sexp(),
+            AtomicExpr::Parenthesized(expr) => expr.to_sexp(options),
             AtomicExpr::Name(name) => Sexp::Name(name.clone()),
+            AtomicExpr::Error(span) => Sexp::Error(*span),
         }
     }
 
     fn span(&self) -> Span {
         match self {
-            AtomicExpr::String(string) => string.span,
-            AtomicExpr::Number(number) => number.span,
+            AtomicExpr::String(string) => string.span(),
+            AtomicExpr::Number(number) => number.span(),
             AtomicExpr::Parenthesized(parenthesized) => {
                 parenthesized.open.span.join(&parenthesized.close.span)
             }
-            AtomicExpr::Name(name) => name.span,
+            AtomicExpr::Name(name) => name.span(),
+            AtomicExpr::Error(span) => *span,
+        }
+    }
+
+    fn node_shape(&self) -> NodeShape {
+        match self {
+            AtomicExpr::Parenthesized(parenthesized) => NodeShape::new(
+                SyntaxKind::Parenthesized,
+                self.span(),
+                parenthesized
+                    .list
+                    .0
+                    .iter()
+                    .map(RightAssocExpr::node_shape)
+                    .collect(),
+            ),
+            AtomicExpr::Error(span) => NodeShape::new(SyntaxKind::Error, *span, vec![]),
+            _ => NodeShape::new(SyntaxKind::Atom, self.span(), vec![]),
         }
     }
 }
@@ -316,47 +902,352 @@ struct ParenthesizedExpr {
     close: Token,
 }
 
+impl ParenthesizedExpr {
+    /// Parentheses group or list, brackets make a vector and braces a map,
+    /// see [`ParseOptions::collections`].
+    fn to_sexp(&self, options: &ParseOptions) -> Sexp {
+        let span = self.open.span.join(&self.close.span);
+        let tagged = |tag: &str, exprs: Vec<Sexp>| {
+            Sexp::List(SexpList::new(
+                Some(Sexp::Name(LiteralName::new(
+                    tag.to_string(),
+                    self.open.span,
+                )))
+                .into_iter()
+                .chain(exprs)
+                .collect(),
+                span,
+            ))
+        };
+        match self.open.kind {
+            TokenKind::LeftBracket => {
+                let exprs = self.list.to_sexps(options);
+                match options.collections {
+                    Collections::Literals => Sexp::Vector(SexpList::new(exprs, span)),
+                    Collections::Tagged => tagged("vector", exprs),
+                }
+            }
+            TokenKind::LeftBrace => {
+                let entries = self.list.0.iter().map(|entry| match entry {
+                    RightAssocExpr::RightAssocExpr { left, right, .. } => {
+                        (left.to_sexp(options), right.to_sexp(options))
+                    }
+                    // Entries without a value were reported while parsing.
+                    _ => (Sexp::Error(entry.span()), Sexp::Error(entry.span())),
+                });
+                match options.collections {
+                    Collections::Literals => Sexp::Map(SexpMap::new(entries.collect(), span)),
+                    Collections::Tagged => tagged(
+                        "map",
+                        entries.flat_map(|(key, value)| [key, value]).collect(),
+                    ),
+                }
+            }
+            // A list of one expression is that expression, so parentheses
+            // only group, like in `(n - 1) !`.
+            _ => match self.list.0.as_slice() {
+                [expr] => expr.to_sexp(options),
+                // Empty parentheses were reported while parsing.
+                [] => Sexp::Error(span),
+                _ => Sexp::List(SexpList::new(self.list.to_sexps(options), span)),
+            },
+        }
+    }
+}
+
 impl List {
-    fn to_sexp(&self) -> Sexp {
-        let inner = self.0.iter().map(|expr| expr.to_sexp()).collect_vec();
-        Sexp::List(inner)
+    /// One S-expression per element.
+    fn to_sexps(&self, options: &ParseOptions) -> Vec<Sexp> {
+        self.0.iter().map(|expr| expr.to_sexp(options)).collect()
     }
 }
 
 impl<'a> Parser<'a> {
     fn new(input_text: &'a str) -> Parser<'a> {
+        Self::with_options(input_text, ParseOptions::default())
+    }
+
+    fn with_options(input_text: &'a str, options: ParseOptions) -> Parser<'a> {
         Self {
             tokenizer: Tokenizer::new(input_text),
+            options,
+            trivia: vec![],
+            last_line_number: 0,
+            line_indentation: 1,
+            bracket_depth: 0,
+            layout_fences: vec![],
+            previous_token_kind: None,
+            recovering: false,
+            errors: vec![],
+            malformed: None,
         }
     }
+
+    /// The comments skipped so far, in source order.
+    fn trivia(&self) -> &[Token] {
+        &self.trivia
+    }
+
+    fn parse_sexps(&mut self) -> ParseResult<Vec<Sexp>> {
+        Ok(self.parse_document()?.to_sexps(&self.options))
+    }
+
+    /// Parses the whole input, failing if anything is left after the list,
+    /// when recovering, leftovers are reported by [`Parser::parse_list`].
+    fn parse_document(&mut self) -> ParseResult<List> {
+        if self.peek_token()?.is_none() {
+            return Ok(List(vec![]));
+        }
+        let list = self.parse_list()?;
+        match self.peek_token_ignoring_layout()? {
+            Some(token) => Err(self.unexpected_token(token, None)),
+            None => Ok(list),
+        }
+    }
+
     fn next_token(&mut self) -> ParseResult<Option<Token>> {
-        self.tokenizer
-            .next_token()
-            .map_err(|err| ParseError::TokenizeError(err))
+        if self.peek_token()?.is_none() {
+            return Ok(None);
+        }
+        self.next_token_ignoring_layout()
+    }
+
+    fn next_token_ignoring_layout(&mut self) -> ParseResult<Option<Token>> {
+        self.skip_trivia()?;
+        let token = match self.malformed.take() {
+            Some(malformed) => Some(malformed),
+            None => self
+                .tokenizer
+                .next_token()
+                .map_err(ParseError::TokenizeError)?,
+        };
+        if let Some(token) = &token {
+            if self.starts_line(token) {
+                self.line_indentation = token.span.start.column_number;
+            }
+            self.last_line_number = token.span.end.line_number;
+            self.previous_token_kind = Some(token.kind.clone());
+        }
+        Ok(token)
+    }
+
+    fn unexpected_token(&self, token: Token, expected: Option<TokenKind>) -> ParseError {
+        ParseError::UnexpectedToken {
+            layout_closed: self.is_layout_boundary(&token),
+            token: Box::new(token),
+            expected: expected.map(Box::new),
+            previous: self.previous_token_kind.clone().map(Box::new),
+        }
     }
+
+    fn unexpected_eof(&mut self, expected: Option<TokenKind>) -> ParseError {
+        let position = match self.peek_token_ignoring_layout() {
+            Ok(Some(token)) => token.span.start,
+            _ => self.tokenizer.position(),
+        };
+        ParseError::UnexpectedEof {
+            expected: expected.map(Box::new),
+            previous: self.previous_token_kind.clone().map(Box::new),
+            span: Span {
+                start: position,
+                end: position,
+            },
+        }
+    }
+
+    fn starts_line(&self, token: &Token) -> bool {
+        token.span.start.line_number > self.last_line_number
+    }
+
+    /// Whether `token` lies beyond the layout boundary of the current form
+    /// or of a colon block in it.
+    fn is_layout_boundary(&self, token: &Token) -> bool {
+        if self.bracket_depth > 0 || !self.starts_line(token) {
+            return false;
+        }
+        let column_number = token.span.start.column_number;
+        self.layout_fences.iter().any(|fence| {
+            token.span.start.line_number > fence.line_number
+                && ((self.options.layout && column_number <= fence.indentation)
+                    || (self.options.newline_as_comma && column_number == fence.indentation))
+        })
+    }
+
+    /// Whether `token` starts a new line that separates it from the current
+    /// form, see [`ParseOptions::newline_as_comma`].
+    fn is_layout_separator(&self, token: &Token) -> bool {
+        self.options.newline_as_comma
+            && self.is_layout_boundary(token)
+            && self
+                .layout_fences
+                .first()
+                .is_some_and(|fence| token.span.start.column_number == fence.indentation)
+    }
+
+    /// The fence of a form that starts at the next token.
+    fn layout_fence_of_next_token(&mut self) -> ParseResult<Option<LayoutFence>> {
+        Ok(self.peek_token_ignoring_layout()?.map(|token| LayoutFence {
+            line_number: token.span.start.line_number,
+            indentation: if self.starts_line(&token) {
+                token.span.start.column_number
+            } else {
+                self.line_indentation
+            },
+        }))
+    }
+
+    fn skip_trivia(&mut self) -> ParseResult<()> {
+        while let Some(token) = self.peek_tokenizer()? {
+            if !token.kind.is_trivia() {
+                break;
+            }
+            self.tokenizer
+                .next_token()
+                .map_err(ParseError::TokenizeError)?;
+            self.trivia.push(token);
+        }
+        Ok(())
+    }
+
     fn parse_list(&mut self) -> ParseResult<List> {
         let mut exprs = vec![];
         loop {
-            let right_assoc_expr = self.parse_right_assoc_expr()?;
-            exprs.push(right_assoc_expr);
-            if self.try_eat_token(TokenKind::Comma)?.is_none() {
-                return Ok(List(exprs));
+            if self.bracket_depth == 0 {
+                self.layout_fences = self.layout_fence_of_next_token()?.into_iter().collect();
+            }
+            let start = match self.peek_token_ignoring_layout()? {
+                Some(token) => token.span.start,
+                None => self.tokenizer.position(),
+            };
+            match self.parse_right_assoc_expr() {
+                Ok(expr) => exprs.push(expr),
+                Err(error) if self.recovering => exprs.push(self.recover_from(start, error)?),
+                Err(error) => return Err(error),
+            }
+            if self.try_eat_separator()? {
+                continue;
+            }
+            match self.peek_token_ignoring_layout()? {
+                // A top-level form that layout closed without a separator,
+                // or a stray closing bracket, is reported and the list goes on.
+                Some(token) if self.recovering && self.bracket_depth == 0 => {
+                    let error = self.unexpected_token(token.clone(), None);
+                    self.errors.push(error);
+                    if matches!(
+                        token.kind,
+                        TokenKind::RightParenthesis
+                            | TokenKind::RightBrace
+                            | TokenKind::RightBracket
+                    ) {
+                        self.next_token_ignoring_layout()?;
+                        if !self.try_eat_separator()?
+                            && self.peek_token_ignoring_layout()?.is_none()
+                        {
+                            return Ok(List(exprs));
+                        }
+                    }
+                }
+                _ => return Ok(List(exprs)),
             }
         }
     }
 
+    /// Records `error` of the form starting at `start` and skips to where the
+    /// list can go on: a `,` or a
+    /// closing bracket of the enclosing list, or the layout boundary of the
+    /// current form. Brackets opened while skipping are skipped as a whole,
+    /// and closing brackets that match nothing are dropped.
+    fn recover_from(&mut self, start: Position, error: ParseError) -> ParseResult<RightAssocExpr> {
+        let mut end = error.span().end;
+        self.errors.push(error);
+        let mut depth = 0;
+        while let Some(token) = self.peek_token()? {
+            match token.kind {
+                TokenKind::Comma if depth == 0 => break,
+                TokenKind::RightParenthesis | TokenKind::RightBrace | TokenKind::RightBracket
+                    if depth == 0 && self.bracket_depth > 0 =>
+                {
+                    break
+                }
+                TokenKind::LeftParenthesis | TokenKind::LeftBrace | TokenKind::LeftBracket => {
+                    depth += 1
+                }
+                TokenKind::RightParenthesis | TokenKind::RightBrace | TokenKind::RightBracket
+                    if depth > 0 =>
+                {
+                    depth -= 1
+                }
+                _ => {}
+            }
+            self.next_token()?;
+            end = token.span.end;
+        }
+        Ok(RightAssocExpr::Error(Span { start, end }))
+    }
+
+    /// Eats the comma that separates list elements, a comma may start a line
+    /// even when that line closes the previous form.
+    fn try_eat_separator(&mut self) -> ParseResult<bool> {
+        match self.peek_token_ignoring_layout()? {
+            Some(Token {
+                kind: TokenKind::Comma,
+                ..
+            }) => {
+                self.next_token_ignoring_layout()?;
+                Ok(true)
+            }
+            Some(token) => Ok(self.is_layout_separator(&token)),
+            None => Ok(false),
+        }
+    }
+
     fn parse_right_assoc_expr(&mut self) -> ParseResult<RightAssocExpr> {
         let left = self.parse_left_assoc_expr()?;
-        if let Some(token) = self.try_eat_token(TokenKind::Colon)? {
+        if self.try_eat_token(TokenKind::Colon)?.is_none() {
+            return Ok(RightAssocExpr::LeftAssocExpr(left));
+        }
+        if !self.options.layout || self.bracket_depth > 0 {
             let right = self.parse_right_assoc_expr()?;
-            Ok(RightAssocExpr::RightAssocExpr {
+            return Ok(RightAssocExpr::RightAssocExpr {
                 left,
-                colon: token,
                 right: Box::new(right),
-            })
-        } else {
-            Ok(RightAssocExpr::LeftAssocExpr(left))
+                continued: vec![],
+            });
+        }
+        // The block after the colon closes at the first line indented at or
+        // left of the colon's line.
+        self.layout_fences.push(LayoutFence {
+            line_number: self.last_line_number,
+            indentation: self.line_indentation,
+        });
+        let block = self.parse_colon_block();
+        self.layout_fences.pop();
+        let (right, continued) = block?;
+        Ok(RightAssocExpr::RightAssocExpr {
+            left,
+            right: Box::new(right),
+            continued,
+        })
+    }
+
+    /// The expression after a colon and, when a deeper colon block closed
+    /// it, the further arguments on the lines still inside this block.
+    fn parse_colon_block(&mut self) -> ParseResult<(RightAssocExpr, Vec<RightAssocExpr>)> {
+        let right = self.parse_right_assoc_expr()?;
+        let mut continued = vec![];
+        while let Some(next) = self.peek_token()? {
+            if matches!(
+                next.kind,
+                TokenKind::Comma
+                    | TokenKind::RightParenthesis
+                    | TokenKind::RightBrace
+                    | TokenKind::RightBracket
+            ) {
+                break;
+            }
+            continued.push(self.parse_right_assoc_expr()?);
         }
+        Ok((right, continued))
     }
 
     fn parse_left_assoc_expr(&mut self) -> ParseResult<LeftAssocExpr> {
@@ -368,11 +1259,10 @@ impl<'a> Parser<'a> {
         &mut self,
         leading: LeftAssocExpr,
     ) -> Result<LeftAssocExpr, ParseError> {
-        if let Some(token) = self.try_eat_token(TokenKind::Dot)? {
+        if self.try_eat_token(TokenKind::Dot)?.is_some() {
             let right = self.parse_operator_function_call_like()?;
             self.try_parse_left_assoc_expr(LeftAssocExpr::LeftAssocExpr {
                 left: Box::new(leading),
-                dot: token,
                 right,
             })
         } else {
@@ -418,10 +1308,11 @@ impl<'a> Parser<'a> {
                         kind:
                             TokenKind::Identifier(_)
                             | TokenKind::StringLiteral(_)
-                            | TokenKind::NumberLiteral(_)
+                            | TokenKind::NumberLiteral { .. }
                             | TokenKind::LeftBrace
                             | TokenKind::LeftParenthesis
-                            | TokenKind::LeftBracket,
+                            | TokenKind::LeftBracket
+                            | TokenKind::Malformed,
                         ..
                     }) => {
                         tail.push(self.parse_atomic_expr()?);
@@ -440,63 +1331,89 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_atomic_expr(&mut self) -> ParseResult<AtomicExpr> {
-        if let Some(token) = self.next_token()? {
+        if let Some(token) = self.peek_token()? {
+            if !matches!(
+                token.kind,
+                TokenKind::Identifier(_)
+                    | TokenKind::StringLiteral(_)
+                    | TokenKind::NumberLiteral { .. }
+                    | TokenKind::LeftParenthesis
+                    | TokenKind::LeftBrace
+                    | TokenKind::LeftBracket
+                    | TokenKind::Malformed
+            ) {
+                return Err(self.unexpected_token(token, None));
+            }
+            self.next_token()?;
             let expr = match token.kind {
-                TokenKind::Identifier(value) => AtomicExpr::Name(LiteralName {
-                    value,
-                    span: token.span,
-                }),
-                TokenKind::StringLiteral(value) => AtomicExpr::String(LiteralString {
-                    value,
-                    span: token.span,
-                }),
-                TokenKind::NumberLiteral(value) => AtomicExpr::Number(LiteralNumber {
-                    value,
-                    span: token.span,
-                }),
+                TokenKind::Identifier(value) => {
+                    AtomicExpr::Name(LiteralName::new(value, token.span))
+                }
+                TokenKind::StringLiteral(value) => {
+                    AtomicExpr::String(LiteralString::new(value, token.span))
+                }
+                TokenKind::NumberLiteral { text, value } => {
+                    AtomicExpr::Number(LiteralNumber::new(value, text, token.span))
+                }
                 TokenKind::LeftParenthesis => AtomicExpr::Parenthesized(
                     self.parse_list_ending_with(token, TokenKind::RightParenthesis)?,
                 ),
-                TokenKind::LeftBrace => AtomicExpr::Parenthesized(
-                    self.parse_list_ending_with(token, TokenKind::RightBrace)?,
-                ),
+                TokenKind::LeftBrace => AtomicExpr::Parenthesized(self.parse_map(token)?),
                 TokenKind::LeftBracket => AtomicExpr::Parenthesized(
                     self.parse_list_ending_with(token, TokenKind::RightBracket)?,
                 ),
-                _ => {
-                    return Err(ParseError::UnexpectedToken {
-                        token,
-                        expected: None,
-                    })
-                }
+                TokenKind::Malformed => AtomicExpr::Error(token.span),
+                _ => unreachable!(),
             };
             Ok(expr)
         } else {
-            Err(ParseError::UnexpectedEof { expected: None })
+            Err(self.unexpected_eof(None))
         }
     }
 
+    /// Peeks the next token, or `None` if the current form ends before it.
     fn peek_token(&mut self) -> ParseResult<Option<Token>> {
-        self.tokenizer
-            .peek_token()
-            .map_err(ParseError::TokenizeError)
+        Ok(self
+            .peek_token_ignoring_layout()?
+            .filter(|token| !self.is_layout_boundary(token)))
+    }
+
+    fn peek_token_ignoring_layout(&mut self) -> ParseResult<Option<Token>> {
+        self.skip_trivia()?;
+        self.peek_tokenizer()
+    }
+
+    /// Peeks the tokenizer, when recovering, a malformed token is recorded
+    /// and read as a [`TokenKind::Malformed`] in its place.
+    fn peek_tokenizer(&mut self) -> ParseResult<Option<Token>> {
+        if let Some(malformed) = &self.malformed {
+            return Ok(Some(malformed.clone()));
+        }
+        match self.tokenizer.peek_token() {
+            Err(error) if self.recovering => {
+                let malformed = Token {
+                    kind: TokenKind::Malformed,
+                    span: error.span(),
+                };
+                self.errors.push(ParseError::TokenizeError(error));
+                self.malformed = Some(malformed.clone());
+                Ok(Some(malformed))
+            }
+            result => result.map_err(ParseError::TokenizeError),
+        }
     }
 
     fn eat_token(&mut self, expected_token_kind: TokenKind) -> ParseResult<Token> {
-        match self.next_token()? {
+        match self.peek_token()? {
             Some(token) => {
                 if token.kind != expected_token_kind {
-                    Err(ParseError::UnexpectedToken {
-                        token,
-                        expected: Some(expected_token_kind),
-                    })
+                    Err(self.unexpected_token(token, Some(expected_token_kind)))
                 } else {
+                    self.next_token()?;
                     Ok(token)
                 }
             }
-            None => Err(ParseError::UnexpectedEof {
-                expected: Some(expected_token_kind),
-            }),
+            None => Err(self.unexpected_eof(Some(expected_token_kind))),
         }
     }
 
@@ -515,11 +1432,36 @@ impl<'a> Parser<'a> {
         open: Token,
         close_kind: TokenKind,
     ) -> ParseResult<ParenthesizedExpr> {
-        let list = self.parse_list()?;
-        let close = self.eat_token(close_kind)?;
+        self.bracket_depth += 1;
+        // An empty vector or map is fine, empty parentheses translate to nothing.
+        let result = match self.peek_token()? {
+            Some(token) if token.kind == close_kind && open.kind != TokenKind::LeftParenthesis => {
+                Ok(List(vec![]))
+            }
+            _ => self.parse_list(),
+        }
+        .and_then(|list| Ok((list, self.eat_token(close_kind)?)));
+        self.bracket_depth -= 1;
+        let (list, close) = result?;
         Ok(ParenthesizedExpr { open, close, list })
     }
 
+    /// `{...}`, whose entries must be `key: value` pairs.
+    fn parse_map(&mut self, open: Token) -> ParseResult<ParenthesizedExpr> {
+        let mut map = self.parse_list_ending_with(open, TokenKind::RightBrace)?;
+        for entry in &mut map.list.0 {
+            if let RightAssocExpr::LeftAssocExpr(expr) = entry {
+                let error = ParseError::MapEntryWithoutValue { span: expr.span() };
+                if !self.recovering {
+                    return Err(error);
+                }
+                self.errors.push(error);
+                *entry = RightAssocExpr::Error(expr.span());
+            }
+        }
+        Ok(map)
+    }
+
     fn parse_operator_function_call_like_component(
         &mut self,
     ) -> ParseResult<OperatorFunctionCallLikeComponent> {
@@ -546,7 +1488,23 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod test_parse {
-    use super::{ParseResult, Parser};
+    use super::{
+        parse_str, parse_str_recovering, parse_str_with_options, parse_str_with_trivia,
+        Collections, ParseError, ParseOptions, ParseResult, Parser, UnaryOperators,
+    };
+    use crate::name::{MixfixName, NameMangler};
+    use crate::sexp::Sexp;
+    use crate::tokenizer::TokenKind;
+    use itertools::Itertools as _;
+
+    /// The translation of every form in `input`, one per line.
+    fn translate(input: &str, options: ParseOptions) -> ParseResult<String> {
+        Ok(parse_str_with_options(input, options)?
+            .iter()
+            .map(|sexp| sexp.stringify())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
 
     #[test]
     fn operator_1() -> ParseResult<()> {
@@ -554,7 +1512,7 @@ mod test_parse {
         let mut parser = Parser::new(input);
         let list = parser.parse_list()?;
         println!("{}", input);
-        println!("{}", list.to_sexp().stringify());
+        println!("{}", list.to_sexps(&ParseOptions::default())[0].stringify());
         Ok(())
     }
 
@@ -564,7 +1522,7 @@ mod test_parse {
         let mut parser = Parser::new(input);
         let list = parser.parse_list()?;
         println!("{}", input);
-        println!("{}", list.to_sexp().stringify());
+        println!("{}", list.to_sexps(&ParseOptions::default())[0].stringify());
         Ok(())
     }
 
@@ -574,9 +1532,426 @@ mod test_parse {
         let mut parser = Parser::new(input);
         let list = parser.parse_list()?;
         println!("{}", input);
-        println!("{}", list.to_sexp().stringify());
+        println!("{}", list.to_sexps(&ParseOptions::default())[0].stringify());
+        Ok(())
+    }
+
+    #[test]
+    fn readme_examples() -> ParseResult<()> {
+        let examples = [
+            ("hello world", "(hello-world)"),
+            ("f 123", "(f 123)"),
+            ("123 f", "(f 123)"),
+            ("plus 2 3", "(plus 2 3)"),
+            ("2 plus 3", "(plus 2 3)"),
+            ("f x y", "(f-x-y)"),
+            ("(f) x y", "(f x-y)"),
+            ("(f) (x) (y)", "(f x y)"),
+            ("x. f y", "(f-y x)"),
+            ("x. f (y)", "(f x y)"),
+            ("f: x y", "(f x-y)"),
+            ("f: (x) (y)", "(f x y)"),
+            ("(x) greater than (y)", "(greater-than x y)"),
+            ("x. greater than (y)", "(greater-than x y)"),
+            ("(x) greater than: y", "(greater-than x y)"),
+            ("x. greater than: y", "(greater-than x y)"),
+            ("n - 1. fib", "(fib (- n 1))"),
+            (
+                "n - 1. fib. +: n - 2. fib",
+                "(+ (fib (- n 1)) (fib (- n 2)))",
+            ),
+            ("1.5.round", "(round 1.5)"),
+        ];
+        for (input, expected) in examples {
+            assert_eq!(translate(input, ParseOptions::default())?, expected);
+        }
         Ok(())
     }
+
+    #[test]
+    fn keeps_number_literals() -> ParseResult<()> {
+        let sexps = parse_str("f 10000000000000001 1.0 0xff 1_000 1e-9")?;
+        let Sexp::List(list) = &sexps[0] else {
+            panic!("not a call")
+        };
+        let literals = list.exprs()[1..]
+            .iter()
+            .map(|expr| match expr {
+                Sexp::Number(number) => (number.text().to_string(), number.value().to_string()),
+                _ => panic!("{} is not a number", expr),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            literals,
+            [
+                ("10000000000000001", "10000000000000001"),
+                ("1.0", "1.0"),
+                ("0xff", "255"),
+                ("1_000", "1000"),
+                ("1e-9", "1e-9"),
+            ]
+            .map(|(text, value)| (text.to_string(), value.to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mixfix_names() -> ParseResult<()> {
+        let spellings = [
+            "(x) greater than (y)",
+            "x. greater than (y)",
+            "(x) greater than: y",
+            "x. greater than: y",
+        ];
+        for spelling in spellings {
+            let sexps = parse_str(spelling)?;
+            let Sexp::List(list) = &sexps[0] else {
+                panic!("{} is not a call", spelling)
+            };
+            let Sexp::Name(head) = &list.exprs()[0] else {
+                panic!("{} has no name", spelling)
+            };
+            assert_eq!(head.value(), "greater-than");
+            assert_eq!(
+                head.mixfix().map(MixfixName::encode).unwrap(),
+                "_greater-than_"
+            );
+        }
+
+        let options = ParseOptions {
+            name_mangler: NameMangler::Mixfix,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate("x. f, f: x, (f) (x) (y), x. (f) (y), n - 1 !", options)?,
+            "(_f x)\n(f_ x)\n(f__ x y)\n(_f_ x y)\n(_-_! n 1)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn name_manglers() -> ParseResult<()> {
+        let input = "(x) greater than (y), x <= y < z";
+        let with_mangler = |name_mangler| ParseOptions {
+            name_mangler,
+            ..Default::default()
+        };
+        let examples = [
+            (NameMangler::Hyphen, "(greater-than x y)\n(<=.< x y z)"),
+            (NameMangler::Mixfix, "(_greater-than_ x y)\n(_<=_<_ x y z)"),
+            (NameMangler::CamelCase, "(greaterThan x y)\n(<=.< x y z)"),
+            (NameMangler::SnakeCase, "(greater_than x y)\n(<=.< x y z)"),
+        ];
+        for (name_mangler, expected) in examples {
+            assert_eq!(translate(input, with_mangler(name_mangler))?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn operator_precedence() -> ParseResult<()> {
+        let input = "a + b * c, x <= y < z, n *: n - 1 . fib, n * (n - 1) !, f x + 2 ^ y ^ 2";
+        let options = ParseOptions {
+            operators: crate::arithmetic_operators(),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(input, options.clone())?,
+            "(+ a (* b c))\n(<=.< x y z)\n(* n (fib (- n 1)))\n(*.! n (- n 1))\n(+ (f-x) (^ 2 (^ y 2)))"
+        );
+
+        let sexps = parse_str_with_options("a + b * c", options)?;
+        let Sexp::List(sum) = &sexps[0] else {
+            panic!("not a call")
+        };
+        let spans = sum
+            .exprs()
+            .iter()
+            .map(|expr| expr.span().slice("a + b * c"))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, ["+", "a", "b * c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn unary_operators() -> ParseResult<()> {
+        let input = "-1, f -1, x - -1, - x, (n - 1) !, x. -, -0x10. abs";
+        assert_eq!(
+            translate(input, ParseOptions::default())?,
+            "-1\n(f -1)\n(- x -1)\n(- x)\n(! (- n 1))\n(- x)\n(abs -16)"
+        );
+        let options = ParseOptions {
+            name_mangler: NameMangler::Mixfix,
+            unary_operators: UnaryOperators::Neg,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(input, options.clone())?,
+            "(neg 1)\n(f_ (neg 1))\n(_-_ x (neg 1))\n(neg x)\n(! (_-_ n 1))\n(- x)\n(_abs (neg 16))"
+        );
+
+        let sexps = parse_str_with_options("f -12", options)?;
+        let Sexp::List(call) = &sexps[0] else {
+            panic!("not a call")
+        };
+        let Sexp::List(negation) = &call.exprs()[1] else {
+            panic!("not a negation")
+        };
+        let spans = negation
+            .exprs()
+            .iter()
+            .map(|expr| expr.span().slice("f -12"))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, ["-", "12"]);
+        assert_eq!(negation.span().slice("f -12"), "-12");
+        Ok(())
+    }
+
+    #[test]
+    fn collections() -> ParseResult<()> {
+        let input = "[a, b c], [], {k: v, \"n\": f: x}, {}, [1]. first, f [x] {y: [z]}";
+        assert_eq!(
+            translate(input, ParseOptions::default())?,
+            "[a (b-c)]\n[]\n{k v, \"n\" (f x)}\n{}\n(first [1])\n(f [x] {y [z]})"
+        );
+        let options = ParseOptions {
+            collections: Collections::Tagged,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(input, options)?,
+            "(vector a (b-c))\n(vector)\n(map k v \"n\" (f x))\n(map)\n(first (vector 1))\n(f (vector x) (map y (vector z)))"
+        );
+
+        let sexps = parse_str("{k: v}")?;
+        let Sexp::Map(map) = &sexps[0] else {
+            panic!("not a map")
+        };
+        assert_eq!(map.span().slice("{k: v}"), "{k: v}");
+        assert_eq!(map.entries()[0].1.span().slice("{k: v}"), "v");
+
+        assert!(matches!(
+            parse_str("{k: v, w}"),
+            Err(ParseError::MapEntryWithoutValue { .. })
+        ));
+        let (sexps, errors) = parse_str_recovering("{k}, g", ParseOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(sexps[1].stringify(), "g");
+        Ok(())
+    }
+
+    #[test]
+    fn layout() -> ParseResult<()> {
+        let input = "
+def (n !):
+  if (n = 0)
+  then 1
+  else: n *:
+    (n - 1) !
+, def (n. fib): f (
+n)
+";
+        let options = ParseOptions {
+            layout: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(input, options.clone())?,
+            "(def (! n) (if-then-else (= n 0) 1 (* n (! (- n 1)))))\n(def (fib n) (f n))",
+        );
+
+        // A dedent closes a colon block, and the form goes on with further
+        // arguments
+        assert_eq!(
+            translate("f:\n  g:\n    x\n  y", options.clone())?,
+            "(f (g x) y)"
+        );
+        assert_eq!(
+            translate(
+                "f:\n  g: x\n  h:\n    y z\n  w\nv",
+                ParseOptions {
+                    newline_as_comma: true,
+                    ..options.clone()
+                }
+            )?,
+            "(f (g x) (h y-z) w)\nv"
+        );
+
+        // A dedent closes the form, so the second line is left over
+        let mut parser = Parser::with_options("a b\nc d", options.clone());
+        assert_eq!(
+            parser.parse_list()?.to_sexps(&options)[0].stringify(),
+            "(a-b)"
+        );
+        assert!(Parser::with_options("a b\nc d", options)
+            .parse_document()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn newline_as_comma() -> ParseResult<()> {
+        let input = "
+a b
+c d
+def (n !):
+  if (n = 0)
+  then 1
+  else: n *: (n - 1) !
+(e
+f), g
+";
+        let options = ParseOptions {
+            newline_as_comma: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(input, options)?,
+            "(a-b)\n(c-d)\n(def (! n) (if-then-else (= n 0) 1 (* n (! (- n 1)))))\n(e-f)\ng",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn spans_every_sexp() -> ParseResult<()> {
+        fn spans(sexp: &Sexp, input: &str, found: &mut Vec<String>) {
+            found.push(sexp.span().slice(input).to_string());
+            if let Sexp::List(list) = sexp {
+                for expr in list.exprs() {
+                    spans(expr, input, found);
+                }
+            }
+        }
+        for (input, expected) in [
+            (
+                "x. greater than: y",
+                vec!["x. greater than: y", "greater than", "x", "y"],
+            ),
+            ("(f) (x), a", vec!["(f) (x)", "f", "x"]),
+            (
+                "n *: (n - 1) !",
+                vec![
+                    "n *: (n - 1) !",
+                    "*",
+                    "n",
+                    "(n - 1) !",
+                    "!",
+                    "n - 1",
+                    "-",
+                    "n",
+                    "1",
+                ],
+            ),
+            (
+                "if (c) then (a, b) else: d",
+                vec![
+                    "if (c) then (a, b) else: d",
+                    "if (c) then (a, b) else",
+                    "c",
+                    "(a, b)",
+                    "a",
+                    "b",
+                    "d",
+                ],
+            ),
+        ] {
+            let mut found = vec![];
+            spans(&parse_str(input)?[0], input, &mut found);
+            assert_eq!(found, expected, "spans of {}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_str_returns_each_form() -> ParseResult<()> {
+        let sexps = parse_str("x. f, 1 plus 2")?;
+        assert_eq!(
+            sexps
+                .iter()
+                .map(|sexp| sexp.stringify())
+                .collect::<Vec<_>>(),
+            ["(f x)", "(plus 1 2)"]
+        );
+        assert_eq!(sexps[0].span().end.column_number, 5);
+        assert!(parse_str("")?.is_empty());
+        assert!(parse_str("f (x").is_err());
+        assert!(parse_str("f )").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn comments_are_skipped() -> ParseResult<()> {
+        let input = "# double it\nn *: #| the #| nested |# factor |# 2";
+        let mut parser = Parser::new(input);
+        let list = parser.parse_list()?;
+        assert_eq!(
+            list.to_sexps(&ParseOptions::default())[0].stringify(),
+            "(* n 2)"
+        );
+        assert_eq!(parser.trivia().len(), 2);
+
+        let (sexps, trivia) = parse_str_with_trivia("f x # last\n, g", ParseOptions::default())?;
+        assert_eq!(sexps.len(), 2);
+        assert_eq!(
+            trivia.iter().map(|token| &token.kind).collect::<Vec<_>>(),
+            [&TokenKind::LineComment(" last".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let recover = |input: &str, options: ParseOptions| {
+            let (sexps, errors) = parse_str_recovering(input, options);
+            (
+                sexps.iter().map(|sexp| sexp.stringify()).join("\n"),
+                errors.iter().map(|error| error.to_string()).collect_vec(),
+            )
+        };
+        assert_eq!(
+            recover("a b, x. , f (y, :), c", ParseOptions::default()),
+            (
+                "(a-b)\n#<error>\n(f (y #<error>))\nc".to_string(),
+                vec!["unexpected `,`".to_string(), "unexpected `:`".to_string()]
+            )
+        );
+        assert_eq!(
+            recover("f (x)), s \"a\\qb\" t, g (", ParseOptions::default()),
+            (
+                "(f x)\n(s-t #<error>)\n#<error>".to_string(),
+                vec![
+                    "unexpected `)`".to_string(),
+                    "invalid escape sequence `\\q`".to_string(),
+                    "unexpected end of input, expected `)`".to_string(),
+                ]
+            )
+        );
+        let layout = ParseOptions {
+            layout: true,
+            newline_as_comma: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            recover(
+                "a:
+b .
+c d",
+                layout
+            )
+            .0,
+            "#<error>\n#<error>\n(c-d)"
+        );
+        assert_eq!(
+            recover(
+                "f: (x
+  y]) z",
+                ParseOptions::default()
+            )
+            .1
+            .len(),
+            1
+        );
+    }
 }
 
 /*
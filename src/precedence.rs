@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+/// How operators of the same precedence group, see [`Fixity`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+    /// `a < b < c` does not group, the chain stays one mixfix call.
+    None,
+}
+
+/// The precedence and associativity of a binary operator, higher precedence
+/// binds tighter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fixity {
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+/// The usual table of arithmetic, comparison and boolean operators, from
+/// `^` binding tightest down to `||`.
+pub fn arithmetic_operators() -> HashMap<String, Fixity> {
+    let levels: [(u8, Associativity, &[&str]); 6] = [
+        (8, Associativity::Right, &["^"]),
+        (7, Associativity::Left, &["*", "/", "%"]),
+        (6, Associativity::Left, &["+", "-"]),
+        (
+            4,
+            Associativity::None,
+            &["<", "<=", ">", ">=", "=", "==", "!=", "/="],
+        ),
+        (3, Associativity::Left, &["&&"]),
+        (2, Associativity::Left, &["||"]),
+    ];
+    levels
+        .into_iter()
+        .flat_map(|(precedence, associativity, symbols)| {
+            symbols.iter().map(move |symbol| {
+                (
+                    symbol.to_string(),
+                    Fixity {
+                        precedence,
+                        associativity,
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+/// Groups `operands[0] operators[0] operands[1] ...` into nested binary
+/// applications by precedence. `None` when an operator is missing from
+/// `table` or when two operators of the same precedence do not associate,
+/// the chain then stays one mixfix call.
+pub(crate) fn group<T, O>(
+    operands: Vec<T>,
+    operators: Vec<(&str, O)>,
+    table: &HashMap<String, Fixity>,
+    mut apply: impl FnMut(O, T, T) -> T,
+) -> Option<T> {
+    let mut operands = operands.into_iter();
+    let mut output = vec![operands.next()?];
+    let mut pending: Vec<(Fixity, O)> = vec![];
+    let mut reduce = |output: &mut Vec<T>, operator: O| {
+        let right = output.pop().unwrap();
+        let left = output.pop().unwrap();
+        output.push(apply(operator, left, right));
+    };
+    for ((symbol, operator), operand) in operators.into_iter().zip(operands) {
+        let fixity = *table.get(symbol)?;
+        while let Some((top, _)) = pending.last() {
+            if top.precedence == fixity.precedence
+                && (top.associativity != fixity.associativity
+                    || fixity.associativity == Associativity::None)
+            {
+                return None;
+            }
+            if top.precedence < fixity.precedence
+                || (top.precedence == fixity.precedence
+                    && fixity.associativity == Associativity::Right)
+            {
+                break;
+            }
+            let (_, top) = pending.pop().unwrap();
+            reduce(&mut output, top);
+        }
+        pending.push((fixity, operator));
+        output.push(operand);
+    }
+    while let Some((_, operator)) = pending.pop() {
+        reduce(&mut output, operator);
+    }
+    output.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Groups a chain of operands and operators separated by spaces, like
+    /// `a + b * c`.
+    fn parenthesize(chain: &str) -> Option<String> {
+        let words = chain.split(' ').collect::<Vec<_>>();
+        let operands = words.iter().step_by(2).map(|word| word.to_string());
+        let operators = words.iter().skip(1).step_by(2).map(|word| (*word, *word));
+        group(
+            operands.collect(),
+            operators.collect(),
+            &arithmetic_operators(),
+            |operator, left, right| format!("({} {} {})", operator, left, right),
+        )
+    }
+
+    #[test]
+    fn groups_by_precedence() {
+        for (chain, expected) in [
+            ("a + b * c", "(+ a (* b c))"),
+            ("a * b + c", "(+ (* a b) c)"),
+            ("a - b - c", "(- (- a b) c)"),
+            ("a ^ b ^ c", "(^ a (^ b c))"),
+            ("a + b < c * d", "(< (+ a b) (* c d))"),
+            ("a", "a"),
+        ] {
+            assert_eq!(
+                parenthesize(chain).as_deref(),
+                Some(expected),
+                "grouping {}",
+                chain
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_chains_that_do_not_group() {
+        assert_eq!(parenthesize("a <= b < c"), None);
+        assert_eq!(parenthesize("a + b <> c"), None);
+    }
+}
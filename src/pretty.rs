@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use crate::{
+    printer::{PrintError, Printer},
+    sexp::Sexp,
+};
+
+/// Indentation of the body of a form with an [`IndentRule::Body`].
+const INDENT: usize = 2;
+
+/// How a list that does not fit on one line is broken, chosen by its head
+/// symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndentRule {
+    /// The arguments are aligned under the first one, the default.
+    Aligned,
+    /// The first `distinguished` arguments stay on the line of the head, the
+    /// rest is indented below it, like `lisp-indent-function` in Emacs.
+    Body { distinguished: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct PrettyOptions {
+    /// Lists longer than this are broken over several lines.
+    pub width: usize,
+    /// Rules by the printed head symbol, heads without a rule are
+    /// [`IndentRule::Aligned`].
+    pub indent_rules: HashMap<String, IndentRule>,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        let body = IndentRule::Body { distinguished: 1 };
+        Self {
+            width: 80,
+            indent_rules: ["def", "define", "if-then-else", "lambda", "let"]
+                .into_iter()
+                .map(|head| (head.to_string(), body))
+                .collect(),
+        }
+    }
+}
+
+/// Prints `sexp` with the atoms of `printer`, breaking lists that are longer
+/// than the width in the style of Wadler's "A prettier printer":
+///
+/// ```text
+/// (def (factorial (n int))
+///   (if-then-else (< n 2)
+///     1
+///     (* n (! (- n 1)))))
+/// ```
+pub fn pretty_print(
+    sexp: &Sexp,
+    printer: &dyn Printer,
+    options: &PrettyOptions,
+) -> Result<String, PrintError> {
+    let doc = to_doc(sexp, printer, options)?;
+    Ok(render(&doc, options.width))
+}
+
+#[derive(Debug)]
+enum Doc {
+    Text(String),
+    /// A space when the enclosing group is flat, otherwise a newline.
+    Line,
+    Concat(Vec<Doc>),
+    /// Printed flat when it fits in the rest of the line.
+    Group(Box<Doc>),
+    /// Lines inside are indented by this much more.
+    Nest(usize, Box<Doc>),
+    /// Lines inside are indented to the column where it starts.
+    Align(Box<Doc>),
+}
+
+fn to_doc(sexp: &Sexp, printer: &dyn Printer, options: &PrettyOptions) -> Result<Doc, PrintError> {
+    let exprs = match sexp {
+        Sexp::List(list) => list.exprs(),
+        Sexp::Vector(vector) => {
+            let (open, close) = printer.vector_delimiters();
+            let elements = vector
+                .exprs()
+                .iter()
+                .map(|expr| to_doc(expr, printer, options))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(bracketed(open, lines(elements), close));
+        }
+        Sexp::Map(map) => {
+            let Some((open, close)) = printer.map_delimiters() else {
+                return to_doc(&map.to_tagged(), printer, options);
+            };
+            let entries = map
+                .entries()
+                .iter()
+                .map(|(key, value)| {
+                    Ok(Doc::Concat(vec![
+                        to_doc(key, printer, options)?,
+                        Doc::Text(" ".to_string()),
+                        to_doc(value, printer, options)?,
+                    ]))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(bracketed(open, lines(entries), close));
+        }
+        atom => return printer.print(atom).map(Doc::Text),
+    };
+    let mut docs = exprs
+        .iter()
+        .map(|expr| to_doc(expr, printer, options))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+    let Some(head) = docs.next() else {
+        return Ok(Doc::Text("()".to_string()));
+    };
+    let rule = match (&exprs[0], &head) {
+        (Sexp::Name(_), Doc::Text(text)) => Some(
+            options
+                .indent_rules
+                .get(text)
+                .copied()
+                .unwrap_or(IndentRule::Aligned),
+        ),
+        _ => None,
+    };
+    let arguments = docs.collect::<Vec<_>>();
+    let mut parts = vec![Doc::Text("(".to_string())];
+    match rule {
+        None => parts.push(Doc::Align(Box::new(lines(
+            std::iter::once(head).chain(arguments),
+        )))),
+        _ if arguments.is_empty() => parts.push(head),
+        Some(IndentRule::Aligned) => {
+            parts.extend([head, Doc::Text(" ".to_string())]);
+            parts.push(Doc::Align(Box::new(lines(arguments))));
+        }
+        Some(IndentRule::Body { distinguished }) => {
+            parts.push(head);
+            let mut arguments = arguments.into_iter();
+            for argument in arguments.by_ref().take(distinguished) {
+                parts.extend([Doc::Text(" ".to_string()), argument]);
+            }
+            let body = arguments
+                .flat_map(|argument| [Doc::Line, argument])
+                .collect::<Vec<_>>();
+            if !body.is_empty() {
+                parts.push(Doc::Nest(INDENT, Box::new(Doc::Concat(body))));
+            }
+        }
+    }
+    parts.push(Doc::Text(")".to_string()));
+    Ok(Doc::Align(Box::new(Doc::Group(Box::new(Doc::Concat(
+        parts,
+    ))))))
+}
+
+/// `elements` between `open` and `close`, one per line when they do not fit.
+fn bracketed(open: &str, elements: Doc, close: &str) -> Doc {
+    Doc::Align(Box::new(Doc::Group(Box::new(Doc::Concat(vec![
+        Doc::Text(open.to_string()),
+        Doc::Align(Box::new(elements)),
+        Doc::Text(close.to_string()),
+    ])))))
+}
+
+/// `docs` separated by [`Doc::Line`].
+fn lines(docs: impl IntoIterator<Item = Doc>) -> Doc {
+    let mut parts = vec![];
+    for doc in docs {
+        if !parts.is_empty() {
+            parts.push(Doc::Line);
+        }
+        parts.push(doc);
+    }
+    Doc::Concat(parts)
+}
+
+/// A document still to be printed, with its indentation and whether its
+/// group is flat.
+type Command<'a> = (usize, bool, &'a Doc);
+
+fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut commands: Vec<Command> = vec![(0, false, doc)];
+    while let Some((indent, flat, doc)) = commands.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line if flat => {
+                out.push(' ');
+                column += 1;
+            }
+            Doc::Line => {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+                column = indent;
+            }
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| (indent, flat, doc)));
+            }
+            Doc::Group(inner) => {
+                let flat = flat
+                    || fits(
+                        width as isize - column as isize,
+                        (indent, true, inner),
+                        &commands,
+                    );
+                commands.push((indent, flat, inner));
+            }
+            Doc::Nest(extra, inner) => commands.push((indent + extra, flat, inner)),
+            Doc::Align(inner) => commands.push((column, flat, inner)),
+        }
+    }
+    out
+}
+
+/// Whether `next` and what follows it up to the next line break fit in
+/// `remaining` columns.
+fn fits(mut remaining: isize, next: Command, rest: &[Command]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut commands = vec![next];
+    while remaining >= 0 {
+        let Some((indent, flat, doc)) = commands.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line if flat => remaining -= 1,
+            Doc::Line => return true,
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| (indent, flat, doc)));
+            }
+            Doc::Group(inner) | Doc::Nest(_, inner) | Doc::Align(inner) => {
+                commands.push((indent, flat, inner))
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_str, printer::Scheme};
+
+    fn pretty(input: &str, width: usize) -> String {
+        let options = PrettyOptions {
+            width,
+            ..PrettyOptions::default()
+        };
+        parse_str(input)
+            .unwrap()
+            .iter()
+            .map(|sexp| pretty_print(sexp, &Scheme, &options).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn breaks_only_long_forms() {
+        assert_eq!(pretty("x. greater than: y", 80), "(greater-than x y)");
+        assert_eq!(pretty("(f) (g) (h)", 5), "(f g\n   h)");
+    }
+
+    #[test]
+    fn indents_bodies_by_head() {
+        let factorial = "def ((n: int) .factorial):\n  if (n < 2) then 1 else: n *: n - 1 .!";
+        assert_eq!(
+            pretty(factorial, 40),
+            "(def (factorial (n int))\n  (if-then-else (< n 2)\n    1\n    (* n (! (- n 1)))))"
+        );
+        assert_eq!(
+            pretty(factorial, 50),
+            "(def (factorial (n int))\n  (if-then-else (< n 2) 1 (* n (! (- n 1)))))"
+        );
+    }
+
+    #[test]
+    fn breaks_collections_like_lists() {
+        assert_eq!(
+            pretty("f [alpha, beta, gamma]", 80),
+            "(f #(alpha beta gamma))"
+        );
+        assert_eq!(
+            pretty("f [alpha, beta, gamma]", 12),
+            "(f #(alpha\n     beta\n     gamma))"
+        );
+        assert_eq!(
+            pretty_print(
+                &parse_str("{key: value, other: value}").unwrap()[0],
+                &Scheme,
+                &PrettyOptions {
+                    width: 16,
+                    ..Default::default()
+                }
+            )
+            .unwrap(),
+            "(map key\n     value\n     other\n     value)"
+        );
+    }
+
+    #[test]
+    fn aligns_arguments_under_the_first() {
+        assert_eq!(
+            pretty("(alpha) plus (beta gamma) (delta)", 20),
+            "(plus alpha\n      (beta-gamma)\n      delta)"
+        );
+        assert_eq!(
+            pretty("f: ((alpha) (beta) (gamma))", 16),
+            "(f (alpha beta\n          gamma))"
+        );
+    }
+}
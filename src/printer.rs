@@ -0,0 +1,490 @@
+use itertools::Itertools as _;
+use num_bigint::BigInt;
+
+use crate::{number::Number, sexp::Sexp};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrintError {
+    /// The reader of the dialect has no syntax for this value.
+    Unrepresentable {
+        value: String,
+        dialect: &'static str,
+    },
+    /// A form that failed to parse, see [`Sexp::Error`].
+    ErrorNode,
+}
+
+impl std::fmt::Display for PrintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrintError::Unrepresentable { value, dialect } => {
+                write!(f, "{} has no syntax for `{}`", dialect, value)
+            }
+            PrintError::ErrorNode => write!(f, "cannot print a form that failed to parse"),
+        }
+    }
+}
+
+impl std::error::Error for PrintError {}
+
+/// Prints S-expressions so that the reader of a Lisp dialect reads back the
+/// same strings, symbols and numbers.
+pub trait Printer {
+    /// The dialect, as named in errors.
+    fn name(&self) -> &'static str;
+
+    fn string(&self, value: &str) -> String;
+
+    fn symbol(&self, name: &str) -> Result<String, PrintError>;
+
+    /// Integers of any size, which all the dialects read in decimal.
+    fn integer(&self, value: &BigInt) -> Result<String, PrintError> {
+        Ok(value.to_string())
+    }
+
+    fn float(&self, value: f64) -> Result<String, PrintError>;
+
+    /// The brackets of a vector literal.
+    fn vector_delimiters(&self) -> (&'static str, &'static str);
+
+    /// The brackets of a map literal with alternating keys and values,
+    /// `None` when the dialect has none, then a map prints as the call
+    /// `(map k v ...)`.
+    fn map_delimiters(&self) -> Option<(&'static str, &'static str)>;
+
+    fn print(&self, sexp: &Sexp) -> Result<String, PrintError> {
+        match sexp {
+            Sexp::List(list) => Ok(format!(
+                "({})",
+                list.exprs()
+                    .iter()
+                    .map(|expr| self.print(expr))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(" ")
+            )),
+            Sexp::Number(number) => match number.value() {
+                Number::Integer(value) => self.integer(value),
+                Number::Float(value) => self.float(*value),
+            },
+            Sexp::Vector(vector) => {
+                let (open, close) = self.vector_delimiters();
+                let elements = vector
+                    .exprs()
+                    .iter()
+                    .map(|expr| self.print(expr))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("{}{}{}", open, elements.join(" "), close))
+            }
+            Sexp::Map(map) => {
+                let Some((open, close)) = self.map_delimiters() else {
+                    return self.print(&map.to_tagged());
+                };
+                let entries = map
+                    .entries()
+                    .iter()
+                    .map(|(key, value)| Ok(format!("{} {}", self.print(key)?, self.print(value)?)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("{}{}{}", open, entries.join(" "), close))
+            }
+            Sexp::String(string) => Ok(self.string(string.value())),
+            Sexp::Name(name) => self.symbol(name.value()),
+            Sexp::Error(_) => Err(PrintError::ErrorNode),
+        }
+    }
+}
+
+fn unrepresentable(printer: &dyn Printer, value: impl ToString) -> PrintError {
+    PrintError::Unrepresentable {
+        value: value.to_string(),
+        dialect: printer.name(),
+    }
+}
+
+/// The dialects with a [`Printer`], R7RS Scheme by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dialect {
+    #[default]
+    Scheme,
+    Clojure,
+    CommonLisp,
+    EmacsLisp,
+}
+
+impl Dialect {
+    pub fn printer(&self) -> &'static dyn Printer {
+        match self {
+            Dialect::Scheme => &Scheme,
+            Dialect::Clojure => &Clojure,
+            Dialect::CommonLisp => &CommonLisp,
+            Dialect::EmacsLisp => &EmacsLisp,
+        }
+    }
+}
+
+/// R7RS: `|...|` for symbols that would not read as themselves, and `\x41;`
+/// escapes for control characters.
+#[derive(Debug, Clone, Copy)]
+pub struct Scheme;
+
+impl Printer for Scheme {
+    fn name(&self) -> &'static str {
+        "Scheme"
+    }
+
+    fn string(&self, value: &str) -> String {
+        escape(value, '"', |character| match character {
+            '\n' => Some("\\n".to_string()),
+            '\t' => Some("\\t".to_string()),
+            '\r' => Some("\\r".to_string()),
+            character if character.is_control() => Some(format!("\\x{:x};", u32::from(character))),
+            _ => None,
+        })
+    }
+
+    fn symbol(&self, name: &str) -> Result<String, PrintError> {
+        let is_plain = |character: char| {
+            character.is_alphanumeric() || "!$%&*/:<=>?^_~+-.@".contains(character)
+        };
+        if !name.is_empty()
+            && name.chars().all(is_plain)
+            && !looks_like_number(name)
+            && (!name.starts_with('.') || name == "...")
+        {
+            Ok(name.to_string())
+        } else {
+            Ok(escape(name, '|', |character| {
+                character
+                    .is_control()
+                    .then(|| format!("\\x{:x};", u32::from(character)))
+            }))
+        }
+    }
+
+    fn float(&self, value: f64) -> Result<String, PrintError> {
+        Ok(match finite_number(value) {
+            Some(number) => number,
+            None if value.is_nan() => "+nan.0".to_string(),
+            None if value > 0.0 => "+inf.0".to_string(),
+            None => "-inf.0".to_string(),
+        })
+    }
+
+    fn vector_delimiters(&self) -> (&'static str, &'static str) {
+        ("#(", ")")
+    }
+
+    fn map_delimiters(&self) -> Option<(&'static str, &'static str)> {
+        None
+    }
+}
+
+/// Clojure and EDN, which have no escapes for symbols, so symbols outside
+/// the documented characters are an error. So are the symbols `nil`, `true`
+/// and `false`, which would read back as the constants.
+#[derive(Debug, Clone, Copy)]
+pub struct Clojure;
+
+impl Printer for Clojure {
+    fn name(&self) -> &'static str {
+        "Clojure"
+    }
+
+    fn string(&self, value: &str) -> String {
+        escape(value, '"', |character| match character {
+            '\n' => Some("\\n".to_string()),
+            '\t' => Some("\\t".to_string()),
+            '\r' => Some("\\r".to_string()),
+            character if character.is_control() => Some(format!("\\u{:04x}", u32::from(character))),
+            _ => None,
+        })
+    }
+
+    fn symbol(&self, name: &str) -> Result<String, PrintError> {
+        let is_plain =
+            |character: char| character.is_alphanumeric() || "*+!-_'?<>=./&$%".contains(character);
+        if !name.is_empty()
+            && !name.starts_with('\'')
+            && name.chars().all(is_plain)
+            && !looks_like_number(name)
+            && !["nil", "true", "false"].contains(&name)
+        {
+            Ok(name.to_string())
+        } else {
+            Err(unrepresentable(self, name))
+        }
+    }
+
+    fn float(&self, value: f64) -> Result<String, PrintError> {
+        Ok(match finite_number(value) {
+            Some(number) => number,
+            None if value.is_nan() => "##NaN".to_string(),
+            None if value > 0.0 => "##Inf".to_string(),
+            None => "##-Inf".to_string(),
+        })
+    }
+
+    fn vector_delimiters(&self) -> (&'static str, &'static str) {
+        ("[", "]")
+    }
+
+    fn map_delimiters(&self) -> Option<(&'static str, &'static str)> {
+        Some(("{", "}"))
+    }
+}
+
+/// Common Lisp with the standard readtable, which upcases symbols: names in
+/// lower case print bare and read as the usual upcased symbols, other names
+/// keep their case in `|...|`. Floats print as doubles, like `1.5d0`.
+#[derive(Debug, Clone, Copy)]
+pub struct CommonLisp;
+
+impl Printer for CommonLisp {
+    fn name(&self) -> &'static str {
+        "Common Lisp"
+    }
+
+    fn string(&self, value: &str) -> String {
+        escape(value, '"', |_| None)
+    }
+
+    fn symbol(&self, name: &str) -> Result<String, PrintError> {
+        let is_plain = |character: char| {
+            !character.is_whitespace()
+                && !character.is_uppercase()
+                && !character.is_control()
+                && !"()'\";`|\\,:#".contains(character)
+        };
+        if !name.is_empty()
+            && name.chars().all(is_plain)
+            && !looks_like_number(name)
+            && name.chars().any(|character| character != '.')
+        {
+            Ok(name.to_string())
+        } else {
+            Ok(escape(name, '|', |_| None))
+        }
+    }
+
+    fn float(&self, value: f64) -> Result<String, PrintError> {
+        let number = finite_number(value).ok_or_else(|| unrepresentable(self, value))?;
+        Ok(match number.find('e') {
+            Some(exponent) => format!("{}d{}", &number[..exponent], &number[exponent + 1..]),
+            None => format!("{}d0", number),
+        })
+    }
+
+    fn vector_delimiters(&self) -> (&'static str, &'static str) {
+        ("#(", ")")
+    }
+
+    fn map_delimiters(&self) -> Option<(&'static str, &'static str)> {
+        None
+    }
+}
+
+/// Emacs Lisp: special characters in symbols are escaped by a backslash, and
+/// control characters in strings by an octal escape.
+#[derive(Debug, Clone, Copy)]
+pub struct EmacsLisp;
+
+impl Printer for EmacsLisp {
+    fn name(&self) -> &'static str {
+        "Emacs Lisp"
+    }
+
+    fn string(&self, value: &str) -> String {
+        escape(value, '"', |character| match character {
+            '\n' => Some("\\n".to_string()),
+            '\t' => Some("\\t".to_string()),
+            character if character.is_control() => Some(format!("\\{:03o}", u32::from(character))),
+            _ => None,
+        })
+    }
+
+    fn symbol(&self, name: &str) -> Result<String, PrintError> {
+        if name.is_empty() {
+            return Ok("##".to_string());
+        }
+        let is_plain = |character: char| {
+            character.is_alphanumeric() || "-+=*/_~!@$%^&:<>{}".contains(character)
+        };
+        let mut symbol = String::new();
+        for (index, character) in name.chars().enumerate() {
+            if !is_plain(character) || (index == 0 && looks_like_number(name)) {
+                symbol.push('\\');
+            }
+            symbol.push(character);
+        }
+        Ok(symbol)
+    }
+
+    fn float(&self, value: f64) -> Result<String, PrintError> {
+        Ok(match finite_number(value) {
+            Some(number) => number,
+            None if value.is_nan() => "0.0e+NaN".to_string(),
+            None if value > 0.0 => "1.0e+INF".to_string(),
+            None => "-1.0e+INF".to_string(),
+        })
+    }
+
+    fn vector_delimiters(&self) -> (&'static str, &'static str) {
+        ("[", "]")
+    }
+
+    fn map_delimiters(&self) -> Option<(&'static str, &'static str)> {
+        Some(("#s(hash-table test equal data (", "))"))
+    }
+}
+
+/// Rust's shortest form that reads back the same and still reads as a float,
+/// like `1.0`, `-0.0` or `1e300`.
+fn finite_number(value: f64) -> Option<String> {
+    value.is_finite().then(|| Number::Float(value).to_string())
+}
+
+/// Whether a reader would take `name` for a number, like `1+`, `-1` or `.5`.
+fn looks_like_number(name: &str) -> bool {
+    let unsigned = name.strip_prefix(['+', '-']).unwrap_or(name);
+    let unsigned = unsigned.strip_prefix('.').unwrap_or(unsigned);
+    unsigned.starts_with(|character: char| character.is_ascii_digit())
+}
+
+/// `value` between `delimiter`s, with the delimiter and backslash escaped by
+/// a backslash and other characters as `escape_character` says.
+fn escape(
+    value: &str,
+    delimiter: char,
+    escape_character: impl Fn(char) -> Option<String>,
+) -> String {
+    let escaped = value
+        .chars()
+        .map(|character| {
+            if character == delimiter || character == '\\' {
+                format!("\\{}", character)
+            } else {
+                escape_character(character).unwrap_or_else(|| character.to_string())
+            }
+        })
+        .join("");
+    format!("{}{}{}", delimiter, escaped, delimiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reader::read_str,
+        sexp::{LiteralName, SexpList},
+        tokenizer::{Position, Span},
+    };
+
+    fn print(printer: &dyn Printer, input: &str) -> Result<String, PrintError> {
+        printer.print(&read_str(input).unwrap()[0])
+    }
+
+    #[test]
+    fn escapes_for_each_reader() {
+        let sexp = "(greater-than \"a\\\"b\\\\\\n\u{1b}\" 1 1.5 0.1)";
+        assert_eq!(
+            print(&Scheme, sexp).unwrap(),
+            "(greater-than \"a\\\"b\\\\\\n\\x1b;\" 1 1.5 0.1)"
+        );
+        assert_eq!(
+            print(&Clojure, sexp).unwrap(),
+            "(greater-than \"a\\\"b\\\\\\n\\u001b\" 1 1.5 0.1)"
+        );
+        assert_eq!(
+            print(&CommonLisp, sexp).unwrap(),
+            "(greater-than \"a\\\"b\\\\\n\u{1b}\" 1 1.5d0 0.1d0)"
+        );
+        assert_eq!(
+            print(&EmacsLisp, sexp).unwrap(),
+            "(greater-than \"a\\\"b\\\\\\n\\033\" 1 1.5 0.1)"
+        );
+    }
+
+    #[test]
+    fn escapes_symbols() {
+        let position = Position {
+            line_number: 1,
+            column_number: 1,
+            character_index: 0,
+            byte_index: 0,
+        };
+        let span = Span {
+            start: position,
+            end: position,
+        };
+        let names = Sexp::List(SexpList::new(
+            ["greaterThan", "greater-than", "1+", "a b", "|", "..."]
+                .into_iter()
+                .map(|name| Sexp::Name(LiteralName::new(name.to_string(), span)))
+                .collect(),
+            span,
+        ));
+        for (printer, expected) in [
+            (
+                &Scheme as &dyn Printer,
+                "(greaterThan greater-than |1+| |a b| |\\|| ...)",
+            ),
+            (
+                &CommonLisp,
+                "(|greaterThan| greater-than |1+| |a b| |\\|| |...|)",
+            ),
+            (
+                &EmacsLisp,
+                "(greaterThan greater-than \\1+ a\\ b \\| \\.\\.\\.)",
+            ),
+        ] {
+            assert_eq!(printer.print(&names).unwrap(), expected);
+        }
+        assert!(Clojure.print(&names).is_err());
+        assert_eq!(
+            print(&Clojure, "(_greater-than_ <= truth)").unwrap(),
+            "(_greater-than_ <= truth)"
+        );
+        for constant in ["nil", "true", "false"] {
+            assert!(matches!(
+                print(&Clojure, constant),
+                Err(PrintError::Unrepresentable { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn prints_collections_for_each_reader() {
+        let sexp = &crate::parse::parse_str("f [1, x] {k: v, \"n\": []}").unwrap()[0];
+        assert_eq!(
+            Scheme.print(sexp).unwrap(),
+            "(f #(1 x) (map k v \"n\" #()))"
+        );
+        assert_eq!(
+            CommonLisp.print(sexp).unwrap(),
+            "(f #(1 x) (map k v \"n\" #()))"
+        );
+        assert_eq!(Clojure.print(sexp).unwrap(), "(f [1 x] {k v \"n\" []})");
+        assert_eq!(
+            EmacsLisp.print(sexp).unwrap(),
+            "(f [1 x] #s(hash-table test equal data (k v \"n\" [])))"
+        );
+        let vector = &crate::parse::parse_str("[1, x]").unwrap()[0];
+        assert_eq!(Scheme.print(vector).unwrap(), "#(1 x)");
+        assert_eq!(CommonLisp.print(vector).unwrap(), "#(1 x)");
+    }
+
+    #[test]
+    fn prints_numbers() {
+        assert_eq!(CommonLisp.float(1e300).unwrap(), "1d300");
+        assert_eq!(print(&CommonLisp, "2.5").unwrap(), "2.5d0");
+        assert_eq!(print(&Scheme, "-0.0").unwrap(), "-0.0");
+        assert_eq!(Scheme.float(f64::INFINITY).unwrap(), "+inf.0");
+        assert_eq!(Clojure.float(f64::NEG_INFINITY).unwrap(), "##-Inf");
+        assert_eq!(EmacsLisp.float(f64::NAN).unwrap(), "0.0e+NaN");
+        assert!(CommonLisp.float(f64::NAN).is_err());
+        assert_eq!(print(&CommonLisp, "1.0").unwrap(), "1.0d0");
+        assert_eq!(
+            print(&Scheme, "(1 1.0 123456789012345678901234567890)").unwrap(),
+            "(1 1.0 123456789012345678901234567890)"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::{
+    number::Number,
+    parse::ParseError,
+    sexp::{LiteralName, LiteralNumber, LiteralString, Sexp, SexpList, SexpMap},
+    tokenizer::{Position, Span, Token, TokenKind, TokenizeError},
+};
+
+/// Reads plain S-expressions, as printed by [`Sexp::stringify`], so that
+/// Lexpr translations can be compared against reference outputs.
+///
+/// Lists are in parentheses, vectors in brackets and maps in braces, with
+/// commas read as whitespace like in `{k v, "n" 1}`. Strings support the escapes `\n`, `\t`, `\\`
+/// and `\"`, numbers are Lexpr number literals like `-1.5`, `1e-9` or
+/// `0xff` with an optional `+`, `'x` is `(quote x)`, `;` comments out
+/// the rest of the line, and anything else up to a delimiter is a symbol.
+pub fn read_str(input_text: &str) -> Result<Vec<Sexp>, ParseError> {
+    let mut reader = Reader {
+        input_characters: input_text.chars().peekable(),
+        current_position: Position {
+            line_number: 1,
+            column_number: 1,
+            character_index: 0,
+            byte_index: 0,
+        },
+    };
+    let mut sexps = vec![];
+    while reader.skip_whitespace_and_comments().is_some() {
+        sexps.push(reader.read_sexp()?);
+    }
+    Ok(sexps)
+}
+
+struct Reader<'a> {
+    input_characters: Peekable<Chars<'a>>,
+    current_position: Position,
+}
+
+impl Reader<'_> {
+    fn next_character(&mut self) -> Option<char> {
+        let character = self.input_characters.next()?;
+        if character == '\n' {
+            self.current_position.line_number += 1;
+            self.current_position.column_number = 1;
+        } else {
+            self.current_position.column_number += 1;
+        }
+        self.current_position.character_index += 1;
+        self.current_position.byte_index += character.len_utf8();
+        Some(character)
+    }
+
+    fn span_from(&self, start_position: Position) -> Span {
+        Span {
+            start: start_position,
+            end: self.current_position,
+        }
+    }
+
+    /// Returns the next significant character without consuming it.
+    fn skip_whitespace_and_comments(&mut self) -> Option<char> {
+        loop {
+            match *self.input_characters.peek()? {
+                ';' => {
+                    while self
+                        .input_characters
+                        .peek()
+                        .is_some_and(|&character| character != '\n')
+                    {
+                        self.next_character();
+                    }
+                }
+                character if character.is_whitespace() || character == ',' => {
+                    self.next_character();
+                }
+                character => return Some(character),
+            }
+        }
+    }
+
+    fn read_sexp(&mut self) -> Result<Sexp, ParseError> {
+        let start_position = self.current_position;
+        let Some(character) = self.skip_whitespace_and_comments() else {
+            return Err(self.unexpected_eof(None));
+        };
+        match character {
+            '(' => {
+                let sexps = self.read_until(')', TokenKind::RightParenthesis)?;
+                Ok(Sexp::List(SexpList::new(
+                    sexps,
+                    self.span_from(start_position),
+                )))
+            }
+            '[' => {
+                let sexps = self.read_until(']', TokenKind::RightBracket)?;
+                Ok(Sexp::Vector(SexpList::new(
+                    sexps,
+                    self.span_from(start_position),
+                )))
+            }
+            '{' => {
+                let mut sexps = self.read_until('}', TokenKind::RightBrace)?.into_iter();
+                let mut entries = vec![];
+                while let Some(key) = sexps.next() {
+                    let Some(value) = sexps.next() else {
+                        return Err(ParseError::MapEntryWithoutValue { span: key.span() });
+                    };
+                    entries.push((key, value));
+                }
+                Ok(Sexp::Map(SexpMap::new(
+                    entries,
+                    self.span_from(start_position),
+                )))
+            }
+            ')' | ']' | '}' => {
+                self.next_character();
+                let kind = match character {
+                    ')' => TokenKind::RightParenthesis,
+                    ']' => TokenKind::RightBracket,
+                    _ => TokenKind::RightBrace,
+                };
+                Err(ParseError::UnexpectedToken {
+                    token: Box::new(Token {
+                        kind,
+                        span: self.span_from(start_position),
+                    }),
+                    expected: None,
+                    previous: None,
+                    layout_closed: false,
+                })
+            }
+            '\'' => {
+                self.next_character();
+                let quote = LiteralName::new("quote".to_string(), self.span_from(start_position));
+                let quoted = self.read_sexp()?;
+                Ok(Sexp::List(SexpList::new(
+                    vec![Sexp::Name(quote), quoted],
+                    self.span_from(start_position),
+                )))
+            }
+            '"' => self.read_string(),
+            _ => Ok(self.read_atom()),
+        }
+    }
+
+    /// The expressions after an opening bracket up to the `close` one.
+    fn read_until(&mut self, close: char, close_kind: TokenKind) -> Result<Vec<Sexp>, ParseError> {
+        self.next_character();
+        let mut sexps = vec![];
+        loop {
+            match self.skip_whitespace_and_comments() {
+                Some(character) if character == close => {
+                    self.next_character();
+                    return Ok(sexps);
+                }
+                Some(_) => sexps.push(self.read_sexp()?),
+                None => return Err(self.unexpected_eof(Some(close_kind))),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Sexp, ParseError> {
+        let start_position = self.current_position;
+        self.next_character();
+        let mut value = String::new();
+        loop {
+            let escape_position = self.current_position;
+            match self.next_character() {
+                Some('"') => {
+                    return Ok(Sexp::String(LiteralString::new(
+                        value,
+                        self.span_from(start_position),
+                    )))
+                }
+                Some('\\') => match self.next_character() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(character @ ('\\' | '"')) => value.push(character),
+                    Some(character) => {
+                        return Err(ParseError::TokenizeError(
+                            TokenizeError::InvalidEscapeSequence {
+                                character,
+                                span: self.span_from(escape_position),
+                            },
+                        ))
+                    }
+                    None => break,
+                },
+                Some(character) => value.push(character),
+                None => break,
+            }
+        }
+        Err(ParseError::TokenizeError(
+            TokenizeError::UnterminatedStringLiteral {
+                span: self.span_from(start_position),
+            },
+        ))
+    }
+
+    /// A number or a symbol, whichever the text up to the next delimiter is.
+    fn read_atom(&mut self) -> Sexp {
+        let start_position = self.current_position;
+        let mut text = String::new();
+        while let Some(&character) = self.input_characters.peek() {
+            if character.is_whitespace() || "()[]{},\"';".contains(character) {
+                break;
+            }
+            text.push(character);
+            self.next_character();
+        }
+        let span = self.span_from(start_position);
+        match read_number(&text) {
+            Some(value) => Sexp::Number(LiteralNumber::new(value, text, span)),
+            None => Sexp::Name(LiteralName::new(text, span)),
+        }
+    }
+
+    fn unexpected_eof(&self, expected: Option<TokenKind>) -> ParseError {
+        ParseError::UnexpectedEof {
+            expected: expected.map(Box::new),
+            previous: None,
+            span: self.span_from(self.current_position),
+        }
+    }
+}
+
+/// The number `text` is, rather than a symbol such as `-`, `1+` or `inf`
+/// that Rust would also parse as a float.
+fn read_number(text: &str) -> Option<Number> {
+    let literal = text
+        .strip_prefix('+')
+        .filter(|rest| !rest.starts_with('-'))
+        .unwrap_or(text);
+    let magnitude = literal.strip_prefix('-').unwrap_or(literal);
+    let magnitude = magnitude.strip_prefix('.').unwrap_or(magnitude);
+    if !magnitude.starts_with(|character: char| character.is_ascii_digit()) {
+        return None;
+    }
+    Number::from_literal(literal).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_str;
+
+    fn read(input: &str) -> String {
+        read_str(input)
+            .unwrap()
+            .iter()
+            .map(Sexp::stringify)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn reads_sexps() {
+        assert_eq!(
+            read("(greater-than x 1.5) ; compare\n'(a \"b\\n\") -2 1+ - .5"),
+            "(greater-than x 1.5)\n(quote (a \"b\\n\"))\n-2\n1+\n-\n0.5"
+        );
+        let sexps = read_str("(f\n  xs)").unwrap();
+        let Sexp::List(list) = &sexps[0] else {
+            panic!("expected a list");
+        };
+        let span = list.exprs()[1].span();
+        assert_eq!(
+            (
+                span.start.line_number,
+                span.start.column_number,
+                span.end.column_number
+            ),
+            (2, 3, 5)
+        );
+    }
+
+    #[test]
+    fn reads_what_stringify_prints() {
+        for lexpr in ["f [a, b c] {k: v, \"n\": [], m: {}}", "[]"] {
+            let sexps = parse_str(lexpr).unwrap();
+            let stringified = sexps[0].stringify();
+            assert_eq!(read(&stringified), stringified);
+            assert!(matches!(
+                read_str(&stringified).unwrap()[0],
+                Sexp::List(_) | Sexp::Vector(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_numbers_back() {
+        let lexpr = "f 1e-9 1.5E16 -2.5e-5 123456789012345678901234567890 0xff";
+        let stringified = parse_str(lexpr).unwrap()[0].stringify();
+        assert_eq!(
+            stringified,
+            "(f 1e-9 1.5e16 -2.5e-5 123456789012345678901234567890 255)"
+        );
+        assert_eq!(read(&stringified), stringified);
+        let Sexp::List(list) = &read_str(&stringified).unwrap()[0] else {
+            panic!("expected a list");
+        };
+        assert!(list.exprs()[1..]
+            .iter()
+            .all(|expr| matches!(expr, Sexp::Number(_))));
+        assert_eq!(
+            read("+1 +-1 1e 0x 1_ -inf nan"),
+            "1\n+-1\n1e\n0x\n1_\n-inf\nnan"
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        let span_of = |input: &str| {
+            let span = read_str(input).unwrap_err().span();
+            (span.start.character_index, span.end.character_index)
+        };
+        assert_eq!(span_of("(a b"), (4, 4));
+        assert_eq!(span_of("a )"), (2, 3));
+        assert_eq!(span_of("\"a\\qb\""), (2, 4));
+        assert_eq!(span_of("'"), (1, 1));
+        assert_eq!(span_of("[a}"), (2, 3));
+        assert_eq!(span_of("{a b c}"), (5, 6));
+    }
+
+    #[test]
+    fn matches_lexpr_translations() {
+        let lexpr = "def ((n: int) .factorial):\n    if (n < 2) then\n        1\n    else:\n        n *: n - 1 .!";
+        let sexp = "(def (factorial (n int)) (if-then-else (< n 2) 1 (* n (! (- n 1)))))";
+        assert_eq!(parse_str(lexpr).unwrap()[0].stringify(), read(sexp));
+    }
+}
@@ -0,0 +1,207 @@
+use itertools::Itertools as _;
+
+use crate::{name::MixfixName, number::Number, tokenizer::Span};
+
+#[derive(Debug, Clone)]
+pub enum Sexp {
+    List(SexpList),
+    /// `[a, b]`, spanning the brackets.
+    Vector(SexpList),
+    /// `{k: v, ...}`, spanning the braces.
+    Map(SexpMap),
+    Number(LiteralNumber),
+    String(LiteralString),
+    Name(LiteralName),
+    /// A form that failed to parse, see [`crate::parse_str_recovering`].
+    Error(Span),
+}
+
+impl Sexp {
+    pub fn stringify(&self) -> String {
+        match self {
+            Sexp::List(list) => {
+                format!(
+                    "({})",
+                    list.exprs.iter().map(|expr| expr.stringify()).join(" ")
+                )
+            }
+            Sexp::Vector(vector) => {
+                format!(
+                    "[{}]",
+                    vector.exprs.iter().map(|expr| expr.stringify()).join(" ")
+                )
+            }
+            Sexp::Map(map) => {
+                format!(
+                    "{{{}}}",
+                    map.entries
+                        .iter()
+                        .map(|(key, value)| format!("{} {}", key.stringify(), value.stringify()))
+                        .join(", ")
+                )
+            }
+            Sexp::Number(number) => number.value.to_string(),
+            Sexp::String(string) => format!("{:#?}", string.value),
+            Sexp::Name(name) => name.value.clone(),
+            Sexp::Error(_) => "#<error>".to_string(),
+        }
+    }
+
+    /// The source range this expression was read from, a list covers the
+    /// whole call including the arguments given by dot and colon.
+    pub fn span(&self) -> Span {
+        match self {
+            Sexp::List(list) | Sexp::Vector(list) => list.span,
+            Sexp::Map(map) => map.span,
+            Sexp::Number(number) => number.span,
+            Sexp::String(string) => string.span,
+            Sexp::Name(name) => name.span,
+            Sexp::Error(span) => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for Sexp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.stringify())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SexpList {
+    exprs: Vec<Sexp>,
+    span: Span,
+}
+impl SexpList {
+    pub(crate) fn new(exprs: Vec<Sexp>, span: Span) -> Self {
+        Self { exprs, span }
+    }
+
+    pub fn exprs(&self) -> &[Sexp] {
+        &self.exprs
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SexpMap {
+    entries: Vec<(Sexp, Sexp)>,
+    span: Span,
+}
+impl SexpMap {
+    pub(crate) fn new(entries: Vec<(Sexp, Sexp)>, span: Span) -> Self {
+        Self { entries, span }
+    }
+
+    /// The keys and values, in source order.
+    pub fn entries(&self) -> &[(Sexp, Sexp)] {
+        &self.entries
+    }
+
+    /// `(map k v ...)`, the call that [`crate::Collections::Tagged`]
+    /// translates a map to.
+    pub(crate) fn to_tagged(&self) -> Sexp {
+        Sexp::List(SexpList::new(
+            Some(Sexp::Name(LiteralName::new("map".to_string(), self.span)))
+                .into_iter()
+                .chain(
+                    self.entries
+                        .iter()
+                        .flat_map(|(key, value)| [key.clone(), value.clone()]),
+                )
+                .collect(),
+            self.span,
+        ))
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralString {
+    value: String,
+    span: Span,
+}
+impl LiteralString {
+    pub(crate) fn new(value: String, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralName {
+    value: String,
+    span: Span,
+    mixfix: Option<MixfixName>,
+}
+impl LiteralName {
+    pub(crate) fn new(value: String, span: Span) -> Self {
+        Self {
+            value,
+            span,
+            mixfix: None,
+        }
+    }
+
+    pub(crate) fn with_mixfix(value: String, span: Span, mixfix: MixfixName) -> Self {
+        Self {
+            value,
+            span,
+            mixfix: Some(mixfix),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The words of a merged name, from the first to the last, the whole
+    /// call is the span of the list.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Where the arguments of the call this name heads sat, `None` for names
+    /// that do not head a call of merged words.
+    pub fn mixfix(&self) -> Option<&MixfixName> {
+        self.mixfix.as_ref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralNumber {
+    value: Number,
+    text: String,
+    span: Span,
+}
+impl LiteralNumber {
+    pub(crate) fn new(value: Number, text: String, span: Span) -> Self {
+        Self { value, text, span }
+    }
+
+    pub fn value(&self) -> &Number {
+        &self.value
+    }
+
+    /// The literal as written, like `0xff` or `1_000`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
@@ -1,41 +1,64 @@
 use std::iter::Peekable;
 use std::str::Chars;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use crate::number::Number;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Position {
     pub line_number: usize,
+    /// In characters, see [`crate::LineIndex`] for UTF-8 and UTF-16 columns.
     pub column_number: usize,
     pub character_index: usize,
+    /// The offset in UTF-8 bytes, for slicing the source text.
+    pub byte_index: usize,
 }
-impl Position {
-    fn min(&self, other: &Position) -> Position {
-        Position {
-            line_number: self.line_number.min(other.line_number),
-            column_number: self.column_number.min(other.column_number),
-            character_index: self.character_index.min(other.character_index),
-        }
+
+/// Positions are ordered by offset, lines and columns follow from it.
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.byte_index.cmp(&other.byte_index)
     }
-    fn max(&self, other: &Position) -> Position {
-        Position {
-            line_number: self.line_number.max(other.line_number),
-            column_number: self.column_number.max(other.column_number),
-            character_index: self.character_index.max(other.character_index),
-        }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// The characters from `start` up to but excluding `end`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
 }
 impl Span {
-    pub(crate) fn join(&self, other: &Span) -> Span {
+    /// The smallest span covering both spans and everything between them.
+    pub fn join(&self, other: &Span) -> Span {
         Span {
-            start: self.start.min(&other.start),
-            end: self.end.max(&other.end),
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
         }
     }
+
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position < self.end
+    }
+
+    /// The number of characters covered.
+    pub fn len(&self) -> usize {
+        self.end.character_index - self.start.character_index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The text of `source` covered, `source` being the text the span was
+    /// read from.
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.byte_index..self.end.byte_index]
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -49,7 +72,11 @@ pub enum TokenKind {
     Operator(String),
     Identifier(String),
     StringLiteral(String),
-    NumberLiteral(f64),
+    /// The literal as written, like `0xff` or `1_000`, and its value.
+    NumberLiteral {
+        text: String,
+        value: Number,
+    },
     LeftParenthesis,
     RightParenthesis,
     LeftBrace,
@@ -59,23 +86,115 @@ pub enum TokenKind {
     Colon,
     Dot,
     Comma,
+    /// `# ...` up to the end of the line, the content excludes the leading `#`.
+    LineComment(String),
+    /// `#| ... |#`, may be nested, the content excludes the outermost delimiters.
+    BlockComment(String),
+    /// Text the tokenizer could not read, which it reports as a
+    /// [`TokenizeError`]. Never produced by the tokenizer itself, a parser
+    /// that recovers from errors reads it in place of the text.
+    Malformed,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Operator(operator) => write!(f, "operator `{}`", operator),
+            TokenKind::Identifier(identifier) => write!(f, "identifier `{}`", identifier),
+            TokenKind::StringLiteral(string) => write!(f, "string {:?}", string),
+            TokenKind::NumberLiteral { text, .. } => write!(f, "number `{}`", text),
+            TokenKind::LeftParenthesis => write!(f, "`(`"),
+            TokenKind::RightParenthesis => write!(f, "`)`"),
+            TokenKind::LeftBrace => write!(f, "`{{`"),
+            TokenKind::RightBrace => write!(f, "`}}`"),
+            TokenKind::LeftBracket => write!(f, "`[`"),
+            TokenKind::RightBracket => write!(f, "`]`"),
+            TokenKind::Colon => write!(f, "`:`"),
+            TokenKind::Dot => write!(f, "`.`"),
+            TokenKind::Comma => write!(f, "`,`"),
+            TokenKind::LineComment(_) | TokenKind::BlockComment(_) => write!(f, "comment"),
+            TokenKind::Malformed => write!(f, "malformed token"),
+        }
+    }
+}
+
+impl TokenKind {
+    /// Trivia carries no meaning for the parser, but is kept for tools
+    /// that need to see the whole source, such as formatters.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::LineComment(_) | TokenKind::BlockComment(_))
+    }
 }
 
 pub struct Tokenizer<'a> {
     input_characters: Peekable<Chars<'a>>,
     current_position: Position,
     cached_next_token: Option<Token>,
+    /// The last character read, which tells a sign from a minus operator.
+    previous_character: Option<char>,
 }
 
 #[derive(Debug)]
-pub(crate) enum TokenizeError {
-    UnexpectedCharacter(char),
-    InvalidEscapeSequence(char),
-    UnterminatedStringLiteral,
-    InvalidNumberFormatMultipleDecimalPoints,
-    FailedToParseNumber(String),
+pub enum TokenizeError {
+    UnexpectedCharacter {
+        character: char,
+        span: Span,
+    },
+    /// The span covers the backslash and the escaped character.
+    InvalidEscapeSequence {
+        character: char,
+        span: Span,
+    },
+    /// The span runs from the opening quote to the end of the input.
+    UnterminatedStringLiteral {
+        span: Span,
+    },
+    /// The span runs from the outermost `#|` to the end of the input.
+    UnterminatedBlockComment {
+        span: Span,
+    },
+    FailedToParseNumber {
+        message: String,
+        span: Span,
+    },
+}
+
+impl TokenizeError {
+    pub fn span(&self) -> Span {
+        match self {
+            TokenizeError::UnexpectedCharacter { span, .. }
+            | TokenizeError::InvalidEscapeSequence { span, .. }
+            | TokenizeError::UnterminatedStringLiteral { span }
+            | TokenizeError::UnterminatedBlockComment { span }
+            | TokenizeError::FailedToParseNumber { span, .. } => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizeError::UnexpectedCharacter { character, .. } => {
+                write!(f, "unexpected character {:?}", character)
+            }
+            TokenizeError::InvalidEscapeSequence { character, .. } => {
+                write!(f, "invalid escape sequence `\\{}`", character)
+            }
+            TokenizeError::UnterminatedStringLiteral { .. } => {
+                write!(f, "unterminated string literal")
+            }
+            TokenizeError::UnterminatedBlockComment { .. } => {
+                write!(f, "unterminated block comment")
+            }
+            TokenizeError::FailedToParseNumber { message, .. } => {
+                write!(f, "failed to parse number: {}", message)
+            }
+        }
+    }
 }
 
+impl std::error::Error for TokenizeError {}
+
 impl<'a> Tokenizer<'a> {
     pub fn new(input_text: &'a str) -> Self {
         Self {
@@ -84,8 +203,10 @@ impl<'a> Tokenizer<'a> {
                 line_number: 1,
                 column_number: 1,
                 character_index: 0,
+                byte_index: 0,
             },
             cached_next_token: None,
+            previous_character: None,
         }
     }
 
@@ -97,6 +218,20 @@ impl<'a> Tokenizer<'a> {
             self.current_position.column_number += 1;
         }
         self.current_position.character_index += 1;
+        self.current_position.byte_index += character.len_utf8();
+        self.previous_character = Some(character);
+    }
+
+    /// The position right after the last character read.
+    pub(crate) fn position(&self) -> Position {
+        self.current_position
+    }
+
+    fn span_from(&self, start_position: Position) -> Span {
+        Span {
+            start: start_position,
+            end: self.current_position,
+        }
     }
 
     fn consume_while<Predicate>(&mut self, predicate: Predicate) -> String
@@ -127,9 +262,9 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    pub(crate) fn next_token(&mut self) -> Result<Option<Token>, TokenizeError> {
+    pub fn next_token(&mut self) -> Result<Option<Token>, TokenizeError> {
         if let Some(token) = self.cached_next_token.take() {
-            return Ok(Some(token));
+            Ok(Some(token))
         } else {
             self.read_next_token()
         }
@@ -186,21 +321,40 @@ impl<'a> Tokenizer<'a> {
                     self.advance_position(character);
                     Ok(TokenKind::Comma)
                 }
+                '#' if self.input_characters.clone().nth(1) == Some('|') => {
+                    self.parse_block_comment()
+                }
+                '#' => Ok(self.parse_line_comment()),
                 '"' => self.parse_string_literal(),
                 '0'..='9' => self.parse_number_literal(),
+                // A minus right before a digit is a sign, unless it follows an
+                // expression directly, as in `n-1`.
+                '-' if self
+                    .peek_after(0)
+                    .is_some_and(|character| character.is_ascii_digit())
+                    && !self.previous_character.is_some_and(|character| {
+                        character.is_alphanumeric() || "_)]}\"".contains(character)
+                    }) =>
+                {
+                    self.parse_number_literal()
+                }
                 character if character.is_alphanumeric() || character == '_' => {
                     Ok(self.parse_alphanumeric_identifier())
                 }
                 character if !character.is_whitespace() => Ok(self.parse_operator()),
-                _ => Err(TokenizeError::UnexpectedCharacter(character)),
+                _ => {
+                    self.input_characters.next();
+                    self.advance_position(character);
+                    Err(TokenizeError::UnexpectedCharacter {
+                        character,
+                        span: self.span_from(start_position),
+                    })
+                }
             }?;
 
             Ok(Some(Token {
                 kind: token,
-                span: Span {
-                    start: start_position,
-                    end: self.current_position,
-                },
+                span: self.span_from(start_position),
             }))
         } else {
             Ok(None)
@@ -221,15 +375,66 @@ impl<'a> Tokenizer<'a> {
         TokenKind::Operator(identifier)
     }
 
+    fn parse_line_comment(&mut self) -> TokenKind {
+        // Consume the `#`
+        self.input_characters.next();
+        self.advance_position('#');
+
+        TokenKind::LineComment(self.consume_while(|character| character != '\n'))
+    }
+
+    fn parse_block_comment(&mut self) -> Result<TokenKind, TokenizeError> {
+        let start_position = self.current_position;
+        // Consume the opening `#|`
+        for character in ['#', '|'] {
+            self.input_characters.next();
+            self.advance_position(character);
+        }
+
+        let mut comment_content = String::new();
+        let mut depth = 1;
+
+        while let Some(character) = self.input_characters.next() {
+            self.advance_position(character);
+            let delimiter = match (character, self.input_characters.peek()) {
+                ('#', Some('|')) => Some(1),
+                ('|', Some('#')) => Some(-1),
+                _ => None,
+            };
+            if let Some(delta) = delimiter {
+                let second_character = self.input_characters.next().unwrap();
+                self.advance_position(second_character);
+                depth += delta;
+                if depth == 0 {
+                    return Ok(TokenKind::BlockComment(comment_content));
+                }
+                comment_content.push(character);
+                comment_content.push(second_character);
+            } else {
+                comment_content.push(character);
+            }
+        }
+        Err(TokenizeError::UnterminatedBlockComment {
+            span: self.span_from(start_position),
+        })
+    }
+
     fn parse_string_literal(&mut self) -> Result<TokenKind, TokenizeError> {
+        let start_position = self.current_position;
         // Consume the opening quote
         self.input_characters.next();
         self.advance_position('"');
 
         let mut string_content = String::new();
         let mut is_escaped = false;
+        let mut escape_position = self.current_position;
+        // Reported once the string is closed, so the tokenizer resumes after it.
+        let mut invalid_escape = None;
 
         while let Some(character) = self.input_characters.next() {
+            if !is_escaped {
+                escape_position = self.current_position;
+            }
             self.advance_position(character);
             match (is_escaped, character) {
                 (true, 'n') => {
@@ -252,50 +457,101 @@ impl<'a> Tokenizer<'a> {
                     is_escaped = true;
                 }
                 (false, '"') => {
-                    return Ok(TokenKind::StringLiteral(string_content));
+                    return match invalid_escape {
+                        Some(error) => Err(error),
+                        None => Ok(TokenKind::StringLiteral(string_content)),
+                    };
                 }
                 (false, character) => {
                     string_content.push(character);
                 }
                 (true, character) => {
-                    return Err(TokenizeError::InvalidEscapeSequence(character));
+                    invalid_escape.get_or_insert(TokenizeError::InvalidEscapeSequence {
+                        character,
+                        span: self.span_from(escape_position),
+                    });
+                    is_escaped = false;
                 }
             }
         }
-        Err(TokenizeError::UnterminatedStringLiteral)
+        Err(TokenizeError::UnterminatedStringLiteral {
+            span: self.span_from(start_position),
+        })
+    }
+
+    /// The character `offset` characters after the next one.
+    fn peek_after(&self, offset: usize) -> Option<char> {
+        self.input_characters.clone().nth(offset + 1)
     }
 
     fn parse_number_literal(&mut self) -> Result<TokenKind, TokenizeError> {
-        let mut number_string = String::new();
-        let mut has_decimal_point = false;
+        let start_position = self.current_position;
+        let mut text = String::new();
+        if self.input_characters.peek() == Some(&'-') {
+            self.input_characters.next();
+            self.advance_position('-');
+            text.push('-');
+        }
 
-        while let Some(&character) = self.input_characters.peek() {
-            match character {
-                '0'..='9' => {
-                    number_string.push(character);
-                    self.input_characters.next();
-                    self.advance_position(character);
-                }
-                '.' if !has_decimal_point => {
-                    has_decimal_point = true;
-                    number_string.push(character);
-                    self.input_characters.next();
+        // A base prefix takes all the letters after it, so that a digit out
+        // of range is an error rather than the start of a name.
+        if self.input_characters.peek() == Some(&'0')
+            && matches!(self.peek_after(0), Some('x' | 'b' | 'o'))
+        {
+            text.push_str(
+                &self.consume_while(|character| character.is_alphanumeric() || character == '_'),
+            );
+        } else {
+            text.push_str(
+                &self.consume_while(|character| character.is_ascii_digit() || character == '_'),
+            );
+            // A dot is a decimal point only between digits, otherwise it
+            // chains, as in `n - 1. fib` or `1.5.round`.
+            if self.input_characters.peek() == Some(&'.')
+                && self
+                    .peek_after(0)
+                    .is_some_and(|character| character.is_ascii_digit())
+            {
+                self.input_characters.next();
+                self.advance_position('.');
+                text.push('.');
+                text.push_str(
+                    &self.consume_while(|character| character.is_ascii_digit() || character == '_'),
+                );
+            }
+            // Likewise an `e` starts an exponent only before its digits.
+            let marker_length = match self.peek_after(0) {
+                Some('+' | '-') => 2,
+                _ => 1,
+            };
+            if matches!(self.input_characters.peek(), Some('e' | 'E'))
+                && self
+                    .peek_after(marker_length - 1)
+                    .is_some_and(|character| character.is_ascii_digit())
+            {
+                for _ in 0..marker_length {
+                    let character = self.input_characters.next().unwrap();
                     self.advance_position(character);
+                    text.push(character);
                 }
-                '.' => return Err(TokenizeError::InvalidNumberFormatMultipleDecimalPoints),
-                _ => break,
+                text.push_str(
+                    &self.consume_while(|character| character.is_ascii_digit() || character == '_'),
+                );
             }
         }
 
-        number_string
-            .parse::<f64>()
-            .map(TokenKind::NumberLiteral)
-            .map_err(|error| TokenizeError::FailedToParseNumber(format!("{}", error)))
+        match Number::from_literal(&text) {
+            Ok(value) => Ok(TokenKind::NumberLiteral { text, value }),
+            Err(message) => Err(TokenizeError::FailedToParseNumber {
+                message,
+                span: self.span_from(start_position),
+            }),
+        }
     }
 
-    pub(crate) fn peek_token(&mut self) -> Result<Option<Token>, TokenizeError> {
+    pub fn peek_token(&mut self) -> Result<Option<Token>, TokenizeError> {
         if let Some(token) = self.cached_next_token.as_ref() {
-            return Ok(Some(token.clone()));
+            Ok(Some(token.clone()))
         } else if let Some(token) = self.read_next_token()? {
             self.cached_next_token = Some(token.clone());
             Ok(Some(token))
@@ -309,6 +565,46 @@ impl<'a> Tokenizer<'a> {
 mod tests {
     use super::*;
 
+    fn number(text: &str) -> TokenKind {
+        TokenKind::NumberLiteral {
+            text: text.to_string(),
+            value: Number::from_literal(text).unwrap(),
+        }
+    }
+
+    fn kinds(input_text: &str) -> Vec<TokenKind> {
+        let mut tokenizer = Tokenizer::new(input_text);
+        std::iter::from_fn(move || tokenizer.next_token().unwrap())
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn joins_spans_across_lines() {
+        let input_text = "abcd éfgh\n  x";
+        let mut tokenizer = Tokenizer::new(input_text);
+        let spans = std::iter::from_fn(move || tokenizer.next_token().transpose())
+            .map(|token| token.unwrap().span)
+            .collect::<Vec<_>>();
+        let joined = spans[2].join(&spans[1]);
+        assert_eq!(
+            (joined.start.line_number, joined.start.column_number),
+            (1, 6)
+        );
+        assert_eq!((joined.end.line_number, joined.end.column_number), (2, 4));
+        assert_eq!(joined, spans[1].join(&spans[2]));
+        assert_eq!(joined.slice(input_text), "éfgh\n  x");
+        assert_eq!(joined.len(), 8);
+        assert!(joined.contains(spans[2].start));
+        assert!(!joined.contains(spans[0].start));
+        assert!(!joined.contains(joined.end));
+        assert!(Span {
+            start: joined.end,
+            end: joined.end
+        }
+        .is_empty());
+    }
+
     #[test]
     fn test_tokenizer() {
         let input_text = r#"identifier123 "string literal" 123.456 [] {} ()"#;
@@ -331,9 +627,91 @@ mod tests {
             TokenKind::StringLiteral("string literal".to_string())
         );
 
+        assert_eq!(tokens.pop().unwrap().kind, number("123.456"));
+    }
+
+    #[test]
+    fn test_comments() {
+        let input_text = "a # line comment\n#| block #| nested |# comment |# b";
+        let mut tokenizer = Tokenizer::new(input_text);
+
+        let tokens: Result<Vec<Token>, _> =
+            std::iter::from_fn(move || tokenizer.next_token().transpose()).collect();
+
+        let kinds = tokens
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+
         assert_eq!(
-            tokens.pop().unwrap().kind,
-            TokenKind::NumberLiteral(123.456)
+            kinds,
+            vec![
+                TokenKind::Identifier("a".to_string()),
+                TokenKind::LineComment(" line comment".to_string()),
+                TokenKind::BlockComment(" block #| nested |# comment ".to_string()),
+                TokenKind::Identifier("b".to_string()),
+            ]
         );
+
+        let mut tokenizer = Tokenizer::new("#| unterminated #| |#");
+        assert!(matches!(
+            tokenizer.next_token(),
+            Err(TokenizeError::UnterminatedBlockComment { .. })
+        ));
+    }
+
+    #[test]
+    fn reads_a_decimal_point_only_before_a_digit() {
+        assert_eq!(
+            kinds("1. fib"),
+            [
+                number("1"),
+                TokenKind::Dot,
+                TokenKind::Identifier("fib".to_string()),
+            ]
+        );
+        assert_eq!(kinds("1.2.3"), [number("1.2"), TokenKind::Dot, number("3")]);
+        assert_eq!(kinds("1.5.f")[..2], [number("1.5"), TokenKind::Dot]);
+    }
+
+    #[test]
+    fn reads_numbers_in_every_notation() {
+        assert_eq!(
+            kinds("0xff 0b1010 0o17 1_000 1e-9 2E+3 1.5e3"),
+            ["0xff", "0b1010", "0o17", "1_000", "1e-9", "2E+3", "1.5e3"].map(number)
+        );
+        assert_eq!(
+            kinds("2 else 1e"),
+            [
+                number("2"),
+                TokenKind::Identifier("else".to_string()),
+                number("1"),
+                TokenKind::Identifier("e".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds("-1 (-2.5) n-1 f-1"),
+            [
+                number("-1"),
+                TokenKind::LeftParenthesis,
+                number("-2.5"),
+                TokenKind::RightParenthesis,
+                TokenKind::Identifier("n".to_string()),
+                TokenKind::Operator("-1".to_string()),
+                TokenKind::Identifier("f".to_string()),
+                TokenKind::Operator("-1".to_string()),
+            ]
+        );
+        for input_text in ["0x", "0b12", "1_", "1__0"] {
+            assert!(
+                matches!(
+                    Tokenizer::new(input_text).next_token(),
+                    Err(TokenizeError::FailedToParseNumber { .. })
+                ),
+                "tokenizing {}",
+                input_text
+            );
+        }
     }
 }